@@ -1,8 +1,14 @@
-use clap::{Arg, ArgAction, Command};
+use chrono::Utc;
+use clap::{Arg, ArgAction, ArgMatches, Command};
 
-use crate::{
-    constatns,
-    foramt::{mode::Mode, sizeformat::SizeFormat, sort::SortKey},
+use rs_xtree::{
+    foramt::{
+        column::Column, json_style::JsonStyle, mode::Mode, output::OutputFormat,
+        sizeformat::SizeFormat, sort::SortKey,
+    },
+    git::status::StatusClass,
+    utils::datetime::parse_since,
+    TreeOptions,
 };
 
 pub fn build_cli() -> Command {
@@ -19,18 +25,59 @@ pub fn build_cli() -> Command {
             Arg::new("ext")
                 .short('e')
                 .long("ext")
-                .help("Filter files by extension")
+                .help("Show only files with the given extensions and the directories containing them (case-insensitive, e.g. rs tar.gz)")
                 .action(clap::ArgAction::Append)
                 .num_args(1..),
         )
+        .arg(
+            Arg::new("exclude_ext")
+                .long("exclude-ext")
+                .help("Hide files with the given extensions")
+                .action(clap::ArgAction::Append)
+                .num_args(1..),
+        )
+        .arg(
+            Arg::new("prune")
+                .long("prune")
+                .help("Hide directories that are empty after filtering")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("ignore")
                 .short('i')
                 .long("ignore")
-                .help("Ignore directories or files")
+                .help("Ignore directories or files matching glob patterns (e.g. '*.log', 'build/**/cache')")
                 .action(clap::ArgAction::Append)
                 .num_args(1..),
         )
+        .arg(
+            Arg::new("include")
+                .short('P')
+                .long("include")
+                .visible_alias("match")
+                .help("Show only files matching glob patterns")
+                .action(clap::ArgAction::Append)
+                .num_args(1..),
+        )
+        .arg(
+            Arg::new("regex")
+                .long("regex")
+                .help("Treat --ignore and --include patterns as regular expressions")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("gitignore")
+                .long("gitignore")
+                .help("Respect .gitignore, .ignore and git excludes (default inside a git repository)")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("no_ignore"),
+        )
+        .arg(
+            Arg::new("no_ignore")
+                .long("no-ignore")
+                .help("Do not respect .gitignore, .ignore or git excludes")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("size")
                 .short('s')
@@ -39,12 +86,45 @@ pub fn build_cli() -> Command {
                 .value_parser(clap::value_parser!(SizeFormat))
                 .conflicts_with("long"),
         )
+        .arg(
+            Arg::new("size_filtered")
+                .long("size-filtered")
+                .help("Count files hidden by --ext, --ignore and -a in directory sizes")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("sort")
                 .short('S')
                 .long("sort")
-                .help("See sort")
-                .value_parser(clap::value_parser!(SortKey)),
+                .help("Sort by comma-separated keys, compared in order (e.g. ext,name or mtime)")
+                .value_parser(clap::value_parser!(SortKey))
+                .value_delimiter(',')
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("reverse")
+                .short('r')
+                .long("reverse")
+                .help("Reverse the sort order")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("dirs_first")
+                .long("dirs-first")
+                .help("List directories before files")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("natural")
+                .long("natural")
+                .help("Compare numbers in names by value (file2 before file10)")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("ignore_case")
+                .long("ignore-case")
+                .help("Sort names and extensions case-insensitively")
+                .action(ArgAction::SetTrue),
         )
         .arg(
             Arg::new("depth")
@@ -53,6 +133,19 @@ pub fn build_cli() -> Command {
                 .help("Set the max depth of tree")
                 .value_parser(clap::value_parser!(u32)),
         )
+        .arg(
+            Arg::new("follow")
+                .long("follow")
+                .help("Descend into symbolic links to directories, skipping links that loop back")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("one_file_system")
+                .short('x')
+                .long("one-file-system")
+                .help("Stay on the filesystem of the root and show mount points as leaves")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("json")
                 .short('j')
@@ -60,6 +153,22 @@ pub fn build_cli() -> Command {
                 .help("output json")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("json_style")
+                .long("json-style")
+                .help("JSON schema: legacy, native (versioned, typed) or tree (same as GNU tree -J)")
+                .value_parser(clap::value_parser!(JsonStyle))
+                .default_value("legacy"),
+        )
+        .arg(
+            Arg::new("ndjson")
+                .long("ndjson")
+                .help("Stream one JSON object per entry while walking, without building the tree in memory")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "json", "markdown", "html", "csv", "tsv", "format", "stats", "diff",
+                ]),
+        )
         .arg(
             Arg::new("markdown")
                 .short('m')
@@ -67,6 +176,42 @@ pub fn build_cli() -> Command {
                 .help("output markdown")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .help("Output format")
+                .value_parser(clap::value_parser!(OutputFormat))
+                .conflicts_with_all(["json", "markdown", "html", "csv", "tsv", "stats"]),
+        )
+        .arg(
+            Arg::new("html")
+                .long("html")
+                .help("output a self-contained interactive HTML page")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["json", "markdown", "stats"]),
+        )
+        .arg(
+            Arg::new("csv")
+                .long("csv")
+                .help("Output a flat listing with one row per entry as CSV")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["json", "markdown", "html", "tsv", "stats"]),
+        )
+        .arg(
+            Arg::new("tsv")
+                .long("tsv")
+                .help("Output a flat listing with one row per entry as TSV")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["json", "markdown", "html", "stats"]),
+        )
+        .arg(
+            Arg::new("columns")
+                .long("columns")
+                .help("Columns of --csv/--tsv: path, type, depth, size, mtime, owner, permissions, git")
+                .value_parser(clap::value_parser!(Column))
+                .value_delimiter(',')
+                .action(ArgAction::Append),
+        )
         .arg(
             Arg::new("git")
                 .short('g')
@@ -74,6 +219,62 @@ pub fn build_cli() -> Command {
                 .help("show git diff status")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("show_ignored")
+                .long("show-ignored")
+                .help("Show git-ignored entries dimmed and marked !! instead of hiding them")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("gitignore"),
+        )
+        .arg(
+            Arg::new("recurse_submodules")
+                .long("recurse-submodules")
+                .help("Show git status inside submodules")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("git_changed")
+                .long("git-changed")
+                .help("Show only entries with git changes, optionally limited to comma-separated classes")
+                .value_parser(clap::value_parser!(StatusClass))
+                .value_delimiter(',')
+                .num_args(0..)
+                .require_equals(true)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("rev")
+                .long("rev")
+                .help("Show the tree of a commit, branch or tag instead of the working directory")
+                .conflicts_with_all(["git", "git_changed"]),
+        )
+        .arg(
+            Arg::new("diff")
+                .long("diff")
+                .value_name("REV1..REV2")
+                .help("Show the changes between two revisions as a tree (an omitted side means HEAD)")
+                .value_parser(parse_revision_range)
+                .conflicts_with_all(["rev", "git", "git_changed"]),
+        )
+        .arg(
+            Arg::new("git_log")
+                .long("git-log")
+                .help("Annotate entries with the last commit that touched them")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("git_churn")
+                .long("git-churn")
+                .help("Show how often entries changed in git history and colour them from cold to hot")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("since")
+                .long("since")
+                .help("Count --git-churn commits since a date (e.g. 2024-01-31 or '3 months')")
+                .value_parser(|value: &str| parse_since(value, Utc::now().timestamp()))
+                .requires("git_churn"),
+        )
         .arg(
             Arg::new("long")
                 .short('l')
@@ -89,6 +290,12 @@ pub fn build_cli() -> Command {
                 .value_parser(clap::value_parser!(Mode))
                 .default_value("sync"),
         )
+        .arg(
+            Arg::new("jobs")
+                .long("jobs")
+                .help("Number of entries processed concurrently in parallel or async mode")
+                .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..)),
+        )
         .arg(
             Arg::new("stats")
                 .long("stats")
@@ -103,3 +310,222 @@ pub fn build_cli() -> Command {
                 .action(ArgAction::SetTrue),
         )
 }
+
+/// ## Summary
+/// コマンドライン引数からツリー構築のオプションを組み立てる
+pub fn tree_options(matches: &ArgMatches) -> TreeOptions {
+    let path = matches.get_one::<String>("path").unwrap();
+    let mut options = TreeOptions::new(path)
+        .extensions(
+            matches
+                .get_many::<String>("ext")
+                .into_iter()
+                .flatten()
+                .cloned(),
+        )
+        .exclude_extensions(
+            matches
+                .get_many::<String>("exclude_ext")
+                .into_iter()
+                .flatten()
+                .cloned(),
+        )
+        .prune(matches.get_flag("prune"))
+        .ignores(
+            matches
+                .get_many::<String>("ignore")
+                .into_iter()
+                .flatten()
+                .cloned(),
+        )
+        .includes(
+            matches
+                .get_many::<String>("include")
+                .into_iter()
+                .flatten()
+                .cloned(),
+        )
+        .regex(matches.get_flag("regex"))
+        .git_integration(matches.get_flag("git"))
+        .show_ignored(matches.get_flag("show_ignored"))
+        .recurse_submodules(matches.get_flag("recurse_submodules"))
+        .git_log(matches.get_flag("git_log"))
+        .git_churn(matches.get_flag("git_churn"))
+        .verbose(matches.get_flag("long"))
+        .mode(matches.get_one::<Mode>("mode").cloned().unwrap())
+        .follow(matches.get_flag("follow"))
+        .one_file_system(matches.get_flag("one_file_system"))
+        .show_hidden(matches.get_flag("all"))
+        .size_filtered(matches.get_flag("size_filtered"))
+        .reverse(matches.get_flag("reverse"))
+        .dirs_first(matches.get_flag("dirs_first"))
+        .natural_sort(matches.get_flag("natural"))
+        .ignore_case(matches.get_flag("ignore_case"));
+
+    if let Some(size) = matches.get_one::<SizeFormat>("size") {
+        options = options.size(size.clone());
+    }
+    if let Some(depth) = matches.get_one::<u32>("depth") {
+        options = options.max_depth(*depth);
+    }
+    if let Some(keys) = matches.get_many::<SortKey>("sort") {
+        options = options.sort_keys(keys.cloned());
+    }
+    if matches.get_flag("no_ignore") {
+        options = options.gitignore(false);
+    } else if matches.get_flag("gitignore") {
+        options = options.gitignore(true);
+    }
+    if matches.contains_id("git_changed") {
+        options = options.git_changed(
+            matches
+                .get_many::<StatusClass>("git_changed")
+                .into_iter()
+                .flatten()
+                .copied(),
+        );
+    }
+    if let Some(rev) = matches.get_one::<String>("rev") {
+        options = options.rev(rev);
+    }
+    if let Some((old, new)) = matches.get_one::<(String, String)>("diff") {
+        options = options.diff(old, new);
+    }
+    if let Some(since) = matches.get_one::<i64>("since") {
+        options = options.since(*since);
+    }
+    if let Some(jobs) = matches.get_one::<usize>("jobs") {
+        options = options.jobs(*jobs);
+    }
+
+    options
+}
+
+/// ## Summary
+/// `rev1..rev2`形式の範囲を比較する2つのリビジョンに分ける
+/// gitと同じく省略した側は`HEAD`になる
+fn parse_revision_range(range: &str) -> Result<(String, String), String> {
+    let Some((old, new)) = range.split_once("..") else {
+        return Err(format!("expected <rev1>..<rev2>: {}", range));
+    };
+    if new.starts_with('.') {
+        return Err(format!("symmetric difference is not supported: {}", range));
+    }
+    let or_head = |rev: &str| {
+        if rev.is_empty() {
+            "HEAD".to_string()
+        } else {
+            rev.to_string()
+        }
+    };
+    Ok((or_head(old), or_head(new)))
+}
+
+pub fn json_style(matches: &ArgMatches) -> JsonStyle {
+    matches
+        .get_one::<JsonStyle>("json_style")
+        .copied()
+        .unwrap_or_default()
+}
+
+pub fn columns(matches: &ArgMatches) -> Vec<Column> {
+    matches
+        .get_many::<Column>("columns")
+        .map(|columns| columns.copied().collect())
+        .unwrap_or_default()
+}
+
+pub fn output_format(matches: &ArgMatches) -> OutputFormat {
+    if let Some(format) = matches.get_one::<OutputFormat>("format") {
+        format.clone()
+    } else if matches.get_flag("json") {
+        OutputFormat::Json
+    } else if matches.get_flag("markdown") {
+        OutputFormat::Markdown
+    } else if matches.get_flag("html") {
+        OutputFormat::Html
+    } else if matches.get_flag("csv") {
+        OutputFormat::Csv
+    } else if matches.get_flag("tsv") {
+        OutputFormat::Tsv
+    } else if matches.get_flag("stats") {
+        OutputFormat::Stats
+    } else {
+        OutputFormat::Standard
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_revision_range() {
+        let range = |old: &str, new: &str| Ok((old.to_string(), new.to_string()));
+        assert_eq!(parse_revision_range("v1..v2"), range("v1", "v2"));
+        assert_eq!(parse_revision_range("main.."), range("main", "HEAD"));
+        assert_eq!(parse_revision_range("..main"), range("HEAD", "main"));
+        assert!(parse_revision_range("main").is_err());
+        assert!(parse_revision_range("a...b").is_err());
+    }
+
+    #[test]
+    fn test_output_format() {
+        let format = |args: &[&str]| {
+            let matches = build_cli().try_get_matches_from(args).unwrap();
+            output_format(&matches)
+        };
+        assert!(matches!(
+            format(&["rsxtree", "--format", "xml"]),
+            OutputFormat::Xml
+        ));
+        assert!(matches!(
+            format(&["rsxtree", "--format=yml"]),
+            OutputFormat::Yaml
+        ));
+        assert!(matches!(format(&["rsxtree", "-j"]), OutputFormat::Json));
+        assert!(matches!(format(&["rsxtree"]), OutputFormat::Standard));
+        assert!(build_cli()
+            .try_get_matches_from(["rsxtree", "--format", "toml", "-j"])
+            .is_err());
+        assert!(matches!(format(&["rsxtree", "--tsv"]), OutputFormat::Tsv));
+        assert!(matches!(
+            format(&["rsxtree", "--format", "csv"]),
+            OutputFormat::Csv
+        ));
+        assert!(build_cli()
+            .try_get_matches_from(["rsxtree", "--csv", "--tsv"])
+            .is_err());
+        for other in ["-j", "--md", "--stats"] {
+            assert!(build_cli()
+                .try_get_matches_from(["rsxtree", "--html", other])
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_columns() {
+        let matches = build_cli()
+            .try_get_matches_from([
+                "rsxtree",
+                "--csv",
+                "--columns",
+                "path,bytes",
+                "--columns",
+                "mode",
+                ".",
+            ])
+            .unwrap();
+        assert_eq!(
+            columns(&matches),
+            [Column::Path, Column::Size, Column::Permissions]
+        );
+        let matches = build_cli()
+            .try_get_matches_from(["rsxtree", "--csv"])
+            .unwrap();
+        assert!(columns(&matches).is_empty());
+        assert!(build_cli()
+            .try_get_matches_from(["rsxtree", "--columns", "inode"])
+            .is_err());
+    }
+}
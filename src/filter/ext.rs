@@ -0,0 +1,75 @@
+/// ## Summary
+/// 拡張子の集合
+/// 大文字小文字を区別せず、`tar.gz`のように複数の`.`を含む拡張子にも対応する
+///
+/// ## Examples
+///```
+/// use rs_xtree::filter::ext::ExtensionSet;
+///
+/// let exts = ExtensionSet::new(["rs", ".TAR.GZ"]);
+/// assert!(exts.matches("main.RS"));
+/// assert!(exts.matches("release.tar.gz"));
+/// assert!(!exts.matches("release.gz"));
+///```
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionSet(Vec<String>);
+
+impl ExtensionSet {
+    pub fn new<I, S>(extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self(
+            extensions
+                .into_iter()
+                .map(|ext| ext.as_ref().trim_start_matches('.').to_lowercase())
+                .filter(|ext| !ext.is_empty())
+                .collect(),
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// ## Summary
+    /// ファイル名がいずれかの拡張子で終わるかどうか
+    /// `.gitignore`のように拡張子の前に名前がない場合は拡張子とみなさない
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.0.iter().any(|ext| {
+            name.len() > ext.len() + 1
+                && name.ends_with(ext.as_str())
+                && name[..name.len() - ext.len()].ends_with('.')
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matches_case_insensitive() {
+        let exts = ExtensionSet::new(["md"]);
+        assert!(exts.matches("README.MD"));
+        assert!(exts.matches("guide.md"));
+        assert!(!exts.matches("guide.mdx"));
+    }
+
+    #[test]
+    fn test_matches_multi_part_extension() {
+        let exts = ExtensionSet::new(["tar.gz"]);
+        assert!(exts.matches("backup.tar.gz"));
+        assert!(!exts.matches("backup.gz"));
+        assert!(!exts.matches("backup.tgz"));
+    }
+
+    #[test]
+    fn test_dotfile_is_not_extension() {
+        let exts = ExtensionSet::new(["gitignore"]);
+        assert!(!exts.matches(".gitignore"));
+        assert!(exts.matches("global.gitignore"));
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match,
+};
+
+use crate::git::open_repository;
+
+const GITIGNORE: &str = ".gitignore";
+const IGNORE: &str = ".ignore";
+
+/// ## Summary
+/// gitと同じ規則で`.gitignore`・`.ignore`・`.git/info/exclude`・`core.excludesFile`を適用するフィルター
+///
+/// ## Note
+/// ディレクトリごとのルールは初めて参照されたときに読み込んでキャッシュし、
+/// ディレクトリの走査が終わったら`forget`で捨てる。キャッシュは走査中のディレクトリの分だけになる
+/// 走査の方法に依存しないので、どのWalkerからも同じように使える
+#[derive(Debug)]
+pub struct GitIgnoreFilter {
+    root: PathBuf,
+    base: PathBuf,
+    base_abs: PathBuf,
+    exclude: Gitignore,
+    global: Gitignore,
+    dirs: RwLock<HashMap<PathBuf, Arc<DirIgnore>>>,
+}
+
+/// ## Summary
+/// 1ディレクトリに置かれたignoreファイル
+#[derive(Debug)]
+struct DirIgnore {
+    ignore: Gitignore,
+    gitignore: Gitignore,
+}
+
+impl GitIgnoreFilter {
+    /// ## Summary
+    /// `base`を含むgitリポジトリを探し、そのワークツリーをルートにしたフィルターを作る
+    ///
+    /// ## Parameters
+    /// - `base`: 走査を開始するパス。判定するパスはこのパスを起点にしたものとして扱う
+    ///
+    /// ## Returns
+    /// リポジトリの中であればフィルター, そうでなければNone
+    pub fn discover(base: &Path) -> Option<Self> {
+        let repo = open_repository(base).ok()?;
+        let workdir = repo.workdir()?.canonicalize().ok()?;
+
+        let exclude = build_matcher(&workdir, &[repo.commondir().join("info").join("exclude")]);
+        let global = repo
+            .config()
+            .and_then(|config| config.get_path("core.excludesFile"))
+            .map(|excludes| build_matcher(&workdir, &[excludes]))
+            .unwrap_or_else(|_| Gitignore::global().0);
+
+        Some(Self {
+            root: workdir,
+            base: base.to_path_buf(),
+            base_abs: base.canonicalize().ok()?,
+            exclude,
+            global,
+            dirs: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// ## Summary
+    /// リポジトリの外で`base`以下の`.gitignore`と`.ignore`だけを適用するフィルターを作る
+    pub fn new(base: &Path) -> Self {
+        let root = base.canonicalize().unwrap_or_else(|_| base.to_path_buf());
+        Self {
+            root: root.clone(),
+            base: base.to_path_buf(),
+            base_abs: root,
+            exclude: Gitignore::empty(),
+            global: Gitignore::global().0,
+            dirs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// ## Summary
+    /// パスが無視されるかどうか
+    /// 深い階層のファイルのルールほど優先され、同じ階層では`.ignore`が`.gitignore`より優先される
+    ///
+    /// ## Parameters
+    /// - `path`: 判定するパス。ルートの外のパスは常に無視されない
+    /// - `is_dir`: ディレクトリかどうか。`dir/`のようなパターンの判定に使う
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let Some(path) = self.absolute(path) else {
+            return false;
+        };
+        if !path.starts_with(&self.root) || path == self.root {
+            return false;
+        }
+
+        for dir in path.ancestors().skip(1) {
+            let rules = self.dir_ignore(dir);
+            for matcher in [&rules.ignore, &rules.gitignore] {
+                match matcher.matched(&path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+            if dir == self.root {
+                break;
+            }
+        }
+
+        for matcher in [&self.exclude, &self.global] {
+            match matcher.matched(&path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+
+        false
+    }
+
+    /// ## Summary
+    /// 走査を終えたディレクトリのルールをキャッシュから捨てる
+    /// 以降にディレクトリの中のパスを判定した場合は読み込み直す
+    pub fn forget(&self, dir: &Path) {
+        if let Some(dir) = self.absolute(dir) {
+            self.dirs.write().unwrap().remove(&dir);
+        }
+    }
+
+    /// ## Summary
+    /// 判定するパスをキャッシュのキーと同じ絶対パスにする
+    fn absolute(&self, path: &Path) -> Option<PathBuf> {
+        match path.strip_prefix(&self.base) {
+            Ok(relative) => Some(self.base_abs.join(relative)),
+            Err(_) => path.canonicalize().ok(),
+        }
+    }
+
+    fn dir_ignore(&self, dir: &Path) -> Arc<DirIgnore> {
+        if let Some(rules) = self.dirs.read().unwrap().get(dir) {
+            return rules.clone();
+        }
+
+        let rules = Arc::new(DirIgnore {
+            ignore: build_matcher(dir, &[dir.join(IGNORE)]),
+            gitignore: build_matcher(dir, &[dir.join(GITIGNORE)]),
+        });
+        self.dirs
+            .write()
+            .unwrap()
+            .entry(dir.to_path_buf())
+            .or_insert(rules)
+            .clone()
+    }
+}
+
+/// ## Summary
+/// ignoreファイルからマッチャーを作る
+/// 存在しないファイルは無視し、読み込めないパターンは警告だけ出す
+fn build_matcher(root: &Path, files: &[PathBuf]) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(root);
+    for file in files.iter().filter(|file| file.is_file()) {
+        if let Some(e) = builder.add(file) {
+            eprintln!("WARNING: {}", e);
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        eprintln!("WARNING: {}", e);
+        Gitignore::empty()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+
+    fn repo_fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        git2::Repository::init(root).unwrap();
+        for d in ["src/generated", "logs", "vendor"] {
+            fs::create_dir_all(root.join(d)).unwrap();
+        }
+        for (file, body) in [
+            (".gitignore", "*.log\n/vendor/\n"),
+            ("src/.gitignore", "generated/\n!keep.log\n"),
+            ("src/keep.log", ""),
+            ("src/debug.log", ""),
+            ("src/main.rs", ""),
+            ("src/generated/out.rs", ""),
+            ("logs/app.log", ""),
+            ("vendor/lib.rs", ""),
+            (".ignore", "notes.txt\n"),
+            ("notes.txt", ""),
+            ("secret.key", ""),
+        ] {
+            fs::write(root.join(file), body).unwrap();
+        }
+        fs::write(root.join(".git/info/exclude"), "*.key\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_discover_outside_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(GitIgnoreFilter::discover(dir.path()).is_none());
+    }
+
+    #[test]
+    fn test_gitignore_rules() {
+        let dir = repo_fixture();
+        let root = dir.path();
+        let filter = GitIgnoreFilter::discover(root).unwrap();
+
+        assert!(filter.is_ignored(&root.join("logs/app.log"), false));
+        assert!(filter.is_ignored(&root.join("vendor"), true));
+        assert!(filter.is_ignored(&root.join("src/generated"), true));
+        assert!(!filter.is_ignored(&root.join("src/main.rs"), false));
+        assert!(!filter.is_ignored(&root.join("logs"), true));
+    }
+
+    #[test]
+    fn test_nested_gitignore_overrides_parent() {
+        let dir = repo_fixture();
+        let filter = GitIgnoreFilter::discover(dir.path()).unwrap();
+
+        assert!(!filter.is_ignored(&dir.path().join("src/keep.log"), false));
+    }
+
+    #[test]
+    fn test_ignore_file_and_info_exclude() {
+        let dir = repo_fixture();
+        let filter = GitIgnoreFilter::discover(dir.path()).unwrap();
+
+        assert!(filter.is_ignored(&dir.path().join("notes.txt"), false));
+        assert!(filter.is_ignored(&dir.path().join("secret.key"), false));
+    }
+
+    #[test]
+    fn test_forget_drops_cached_rules() {
+        let dir = repo_fixture();
+        let root = dir.path();
+        let filter = GitIgnoreFilter::discover(root).unwrap();
+
+        assert!(filter.is_ignored(&root.join("src/debug.log"), false));
+        assert_eq!(filter.dirs.read().unwrap().len(), 2);
+        filter.forget(&root.join("src"));
+        assert_eq!(filter.dirs.read().unwrap().len(), 1);
+
+        // 捨てたルールは必要になれば読み込み直す
+        assert!(!filter.is_ignored(&root.join("src/keep.log"), false));
+    }
+
+    #[test]
+    fn test_filter_from_subdirectory_uses_repository_root() {
+        let dir = repo_fixture();
+        let src = dir.path().join("src");
+        let filter = GitIgnoreFilter::discover(&src).unwrap();
+
+        assert!(filter.is_ignored(&src.join("generated"), true));
+        assert!(filter.is_ignored(&src.join("debug.log"), false));
+        assert!(!filter.is_ignored(&src.join("keep.log"), false));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod ext;
+pub mod gitignore;
+pub mod pattern;
@@ -0,0 +1,128 @@
+use std::path::{Component, Path};
+
+use anyhow::Result;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::RegexSet;
+
+/// ## Summary
+/// `--ignore`・`--include`で指定されたパターンの集合
+/// ファイル名とルートからの相対パスのどちらかにマッチすればマッチとみなす
+///
+/// ## Examples
+///```
+/// use std::path::Path;
+/// use rs_xtree::filter::pattern::PatternSet;
+///
+/// let patterns = PatternSet::glob(&["*.log", "build/*/cache"]).unwrap();
+/// assert!(patterns.is_match("app.log", Path::new("logs/app.log")));
+/// assert!(patterns.is_match("cache", Path::new("build/debug/cache")));
+/// assert!(!patterns.is_match("cache", Path::new("cache")));
+///```
+#[derive(Debug, Clone)]
+pub struct PatternSet {
+    matcher: Matcher,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Glob(GlobSet),
+    Regex(RegexSet),
+}
+
+impl PatternSet {
+    /// ## Summary
+    /// globパターンからPatternSetを作る
+    /// `*`は`/`にマッチせず、`**`は任意の階層にマッチする
+    pub fn glob<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = GlobBuilder::new(pattern.as_ref())
+                .literal_separator(true)
+                .build()?;
+            builder.add(glob);
+        }
+
+        Ok(Self {
+            matcher: Matcher::Glob(builder.build()?),
+        })
+    }
+
+    /// ## Summary
+    /// 正規表現からPatternSetを作る
+    pub fn regex<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let patterns = patterns.iter().map(|pattern| pattern.as_ref());
+        Ok(Self {
+            matcher: Matcher::Regex(RegexSet::new(patterns)?),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match &self.matcher {
+            Matcher::Glob(set) => set.is_empty(),
+            Matcher::Regex(set) => set.is_empty(),
+        }
+    }
+
+    /// ## Summary
+    /// ファイル名かルートからの相対パスのどちらかがマッチするかどうか
+    ///
+    /// ## Parameters
+    /// - `name`: ファイル名
+    /// - `relative`: ルートからの相対パス。OSによらず`/`区切りで判定する
+    pub fn is_match(&self, name: &str, relative: &Path) -> bool {
+        let relative = to_slash(relative);
+        match &self.matcher {
+            Matcher::Glob(set) => set.is_match(name) || set.is_match(&relative),
+            Matcher::Regex(set) => set.is_match(name) || set.is_match(&relative),
+        }
+    }
+}
+
+fn to_slash(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_glob_matches_basename() {
+        let patterns = PatternSet::glob(&["*.log", "target"]).unwrap();
+
+        assert!(patterns.is_match("app.log", Path::new("logs/app.log")));
+        assert!(patterns.is_match("target", Path::new("target")));
+        assert!(!patterns.is_match("main.rs", Path::new("src/main.rs")));
+    }
+
+    #[test]
+    fn test_glob_matches_relative_path() {
+        let patterns = PatternSet::glob(&["build/*/cache", "docs/**/*.png"]).unwrap();
+
+        assert!(patterns.is_match("cache", Path::new("build/debug/cache")));
+        assert!(!patterns.is_match("cache", Path::new("build/debug/x/cache")));
+        assert!(patterns.is_match("a.png", Path::new("docs/a.png")));
+        assert!(patterns.is_match("b.png", Path::new("docs/img/raw/b.png")));
+    }
+
+    #[test]
+    fn test_regex_matches() {
+        let patterns = PatternSet::regex(&[r"^test_.*\.rs$", r"^src/gen/"]).unwrap();
+
+        assert!(patterns.is_match("test_walker.rs", Path::new("tests/test_walker.rs")));
+        assert!(patterns.is_match("out.rs", Path::new("src/gen/out.rs")));
+        assert!(!patterns.is_match("walker.rs", Path::new("src/walker.rs")));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_error() {
+        assert!(PatternSet::glob(&["a[b"]).is_err());
+        assert!(PatternSet::regex(&["(unclosed"]).is_err());
+    }
+}
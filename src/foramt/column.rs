@@ -0,0 +1,89 @@
+use std::str::FromStr;
+
+use clap::{builder::PossibleValue, ValueEnum};
+
+/// ## Summary
+/// `--csv`・`--tsv`で出力する列
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    /// ルートからの相対パス
+    Path,
+    /// `directory`・`file`・`symlink`
+    Type,
+    /// ルートを0とする深さ
+    Depth,
+    /// バイト数
+    Size,
+    /// 最終更新日時(RFC 3339)
+    Mtime,
+    /// 所有者のユーザー名。解決できない場合はUID
+    Owner,
+    /// `ls -l`形式のパーミッション
+    Permissions,
+    /// porcelain形式のgit status
+    Git,
+}
+
+impl Column {
+    /// ## Summary
+    /// 列を指定しなかった場合に出力する列
+    pub const ALL: [Column; 8] = [
+        Column::Path,
+        Column::Type,
+        Column::Depth,
+        Column::Size,
+        Column::Mtime,
+        Column::Owner,
+        Column::Permissions,
+        Column::Git,
+    ];
+
+    /// ## Summary
+    /// ヘッダー行に出力する列名
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Path => "path",
+            Column::Type => "type",
+            Column::Depth => "depth",
+            Column::Size => "size",
+            Column::Mtime => "mtime",
+            Column::Owner => "owner",
+            Column::Permissions => "permissions",
+            Column::Git => "git",
+        }
+    }
+}
+
+impl ValueEnum for Column {
+    fn value_variants<'a>() -> &'a [Self] {
+        &Self::ALL
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        let value = PossibleValue::new(self.name());
+        match self {
+            Column::Size => Some(value.alias("bytes")),
+            Column::Permissions => Some(value.alias("mode")),
+            Column::Git => Some(value.alias("status")),
+            _ => Some(value),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "path" => Ok(Column::Path),
+            "type" => Ok(Column::Type),
+            "depth" => Ok(Column::Depth),
+            "size" | "bytes" => Ok(Column::Size),
+            "mtime" => Ok(Column::Mtime),
+            "owner" => Ok(Column::Owner),
+            "permissions" | "mode" => Ok(Column::Permissions),
+            "git" | "status" => Ok(Column::Git),
+            _ => Err(format!("Invalid column:{}", s)),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::str::FromStr;
+
+use clap::{builder::PossibleValue, ValueEnum};
+
+/// ## Summary
+/// JSON出力の形式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonStyle {
+    /// `TreeNode`をそのままシリアライズした従来の形式
+    #[default]
+    Legacy,
+    /// `type`・`path`・数値のサイズと日時を持つバージョン付きの形式
+    Native,
+    /// GNU treeの`-J`と同じ形式
+    Tree,
+}
+
+impl ValueEnum for JsonStyle {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Legacy, Self::Native, Self::Tree]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            JsonStyle::Legacy => Some(PossibleValue::new("legacy")),
+            JsonStyle::Native => Some(PossibleValue::new("native")),
+            JsonStyle::Tree => Some(PossibleValue::new("tree").alias("gnu")),
+        }
+    }
+}
+
+impl FromStr for JsonStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "legacy" => Ok(JsonStyle::Legacy),
+            "native" => Ok(JsonStyle::Native),
+            "tree" | "gnu" => Ok(JsonStyle::Tree),
+            _ => Err(format!("Invalid json style:{}", s)),
+        }
+    }
+}
@@ -1,3 +1,5 @@
+pub mod column;
+pub mod json_style;
 pub mod mode;
 pub mod output;
 pub mod sizeformat;
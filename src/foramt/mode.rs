@@ -11,7 +11,7 @@ pub enum Mode {
 
 impl ValueEnum for Mode {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Self::Sync, Self::Parallel]
+        &[Self::Sync, Self::Parallel, Self::Async]
     }
 
     fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
@@ -1,7 +1,68 @@
-#[derive(Debug)]
+use std::str::FromStr;
+
+use clap::{builder::PossibleValue, ValueEnum};
+
+#[derive(Debug, Clone)]
 pub enum OutputFormat {
     Standard,
     Json,
     Markdown,
+    Html,
+    Yaml,
+    Toml,
+    Xml,
+    Csv,
+    Tsv,
     Stats,
 }
+
+impl ValueEnum for OutputFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::Standard,
+            Self::Json,
+            Self::Markdown,
+            Self::Html,
+            Self::Yaml,
+            Self::Toml,
+            Self::Xml,
+            Self::Csv,
+            Self::Tsv,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            OutputFormat::Standard => Some(PossibleValue::new("tree")),
+            OutputFormat::Json => Some(PossibleValue::new("json")),
+            OutputFormat::Markdown => Some(PossibleValue::new("md").alias("markdown")),
+            OutputFormat::Html => Some(PossibleValue::new("html")),
+            OutputFormat::Yaml => Some(PossibleValue::new("yaml").alias("yml")),
+            OutputFormat::Toml => Some(PossibleValue::new("toml")),
+            OutputFormat::Xml => Some(PossibleValue::new("xml")),
+            OutputFormat::Csv => Some(PossibleValue::new("csv")),
+            OutputFormat::Tsv => Some(PossibleValue::new("tsv")),
+            // 集計は--statsで指定する
+            OutputFormat::Stats => None,
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tree" => Ok(OutputFormat::Standard),
+            "json" => Ok(OutputFormat::Json),
+            "md" | "markdown" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "toml" => Ok(OutputFormat::Toml),
+            "xml" => Ok(OutputFormat::Xml),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(format!("Invalid output format:{}", s)),
+        }
+    }
+}
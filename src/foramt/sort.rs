@@ -1,26 +1,61 @@
-use std::str::FromStr;
+use std::{cmp::Ordering, str::FromStr};
 
 use clap::{builder::PossibleValue, ValueEnum};
 
-#[derive(Clone, Debug)]
+use crate::utils::datetime::TimeKind;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum SortKey {
     Name,
     Size,
-    Time,
+    /// 最終更新日時。`t`/`time`もこれになる
+    Mtime,
+    Atime,
+    Ctime,
+    Btime,
     Ext,
+    /// gitの履歴での変更回数。`--git-churn`が有効になる
+    Churn,
+}
+
+impl SortKey {
+    /// ## Summary
+    /// ソートに必要なタイムスタンプの種類
+    pub fn time_kind(&self) -> Option<TimeKind> {
+        match self {
+            SortKey::Mtime => Some(TimeKind::Modified),
+            SortKey::Atime => Some(TimeKind::Accessed),
+            SortKey::Ctime => Some(TimeKind::Changed),
+            SortKey::Btime => Some(TimeKind::Born),
+            _ => None,
+        }
+    }
 }
 
 impl ValueEnum for SortKey {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Self::Name, Self::Size, Self::Time, Self::Ext]
+        &[
+            Self::Name,
+            Self::Size,
+            Self::Mtime,
+            Self::Atime,
+            Self::Ctime,
+            Self::Btime,
+            Self::Ext,
+            Self::Churn,
+        ]
     }
 
     fn to_possible_value(&self) -> Option<clap::builder::PossibleValue> {
         match self {
             SortKey::Name => Some(PossibleValue::new("n").alias("name")),
             SortKey::Size => Some(PossibleValue::new("s").alias("size")),
-            SortKey::Time => Some(PossibleValue::new("t").alias("time")),
+            SortKey::Mtime => Some(PossibleValue::new("t").aliases(["time", "mtime"])),
+            SortKey::Atime => Some(PossibleValue::new("atime")),
+            SortKey::Ctime => Some(PossibleValue::new("ctime")),
+            SortKey::Btime => Some(PossibleValue::new("btime")),
             SortKey::Ext => Some(PossibleValue::new("e").alias("ext")),
+            SortKey::Churn => Some(PossibleValue::new("churn")),
         }
     }
 }
@@ -32,9 +67,169 @@ impl FromStr for SortKey {
         match s {
             "n" | "name" => Ok(SortKey::Name),
             "s" | "size" => Ok(SortKey::Size),
-            "t" | "tiem" => Ok(SortKey::Time),
+            "t" | "time" | "mtime" => Ok(SortKey::Mtime),
+            "atime" => Ok(SortKey::Atime),
+            "ctime" => Ok(SortKey::Ctime),
+            "btime" => Ok(SortKey::Btime),
             "e" | "ext" => Ok(SortKey::Ext),
-            _ => Err(format!("Invalid size format:{}", s)),
+            "churn" => Ok(SortKey::Churn),
+            _ => Err(format!("Invalid sort key:{}", s)),
+        }
+    }
+}
+
+/// ## Summary
+/// ソートの指定
+/// `keys`を先頭から順に比較し、等しい場合は次のキーで比較する
+#[derive(Clone, Debug, Default)]
+pub struct SortSpec {
+    pub keys: Vec<SortKey>,
+    /// 降順にする。`dirs_first`には影響しない
+    pub reverse: bool,
+    /// ディレクトリをファイルより先に並べる
+    pub dirs_first: bool,
+    /// 名前に含まれる数字を数値として比較する(`file2` < `file10`)
+    pub natural: bool,
+    /// 名前と拡張子を大文字小文字を区別せずに比較する
+    pub ignore_case: bool,
+}
+
+impl SortSpec {
+    /// ## Summary
+    /// ソートが必要かどうか
+    /// キーがなくても他の指定があれば名前順で並べ替える
+    pub fn is_active(&self) -> bool {
+        !self.keys.is_empty() || self.reverse || self.dirs_first || self.natural || self.ignore_case
+    }
+
+    /// ## Summary
+    /// 比較に使うキー。指定がなければ名前
+    pub fn keys(&self) -> &[SortKey] {
+        if self.keys.is_empty() {
+            &[SortKey::Name]
+        } else {
+            &self.keys
         }
     }
+
+    pub fn contains(&self, key: &SortKey) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// ## Summary
+    /// キーに含まれるタイムスタンプの種類
+    pub fn time_kinds(&self) -> Vec<TimeKind> {
+        self.keys.iter().filter_map(SortKey::time_kind).collect()
+    }
+
+    /// ## Summary
+    /// 名前を比較する
+    /// `natural`・`ignore_case`の指定に従い、大文字小文字だけが違う場合は元の文字列で比較する
+    pub fn compare_str(&self, a: &str, b: &str) -> Ordering {
+        let (la, lb) = if self.ignore_case {
+            (a.to_lowercase(), b.to_lowercase())
+        } else {
+            (a.to_string(), b.to_string())
+        };
+
+        let ordering = if self.natural {
+            natural_cmp(&la, &lb)
+        } else {
+            la.cmp(&lb)
+        };
+        ordering.then_with(|| a.cmp(b))
+    }
+}
+
+/// ## Summary
+/// 数字の並びを数値として比較する自然順の比較
+///
+/// ## Examples
+///```
+/// use std::cmp::Ordering;
+/// use rs_xtree::foramt::sort::natural_cmp;
+///
+/// assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+/// assert_eq!(natural_cmp("v1.10.0", "v1.9.3"), Ordering::Greater);
+///```
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let na = take_digits(&mut a);
+                let nb = take_digits(&mut b);
+                // 先頭の0を除いた桁数、数字の並び、0を含めた桁数の順で比較する
+                let ta = na.trim_start_matches('0');
+                let tb = nb.trim_start_matches('0');
+                let ordering = ta
+                    .len()
+                    .cmp(&tb.len())
+                    .then_with(|| ta.cmp(tb))
+                    .then_with(|| na.len().cmp(&nb.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(ca), Some(cb)) => {
+                if ca != cb {
+                    return ca.cmp(&cb);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().copied() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        digits.push(c);
+        chars.next();
+    }
+    digits
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("file10", "file10"), Ordering::Equal);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("file02", "file2"), Ordering::Greater);
+        assert_eq!(natural_cmp("v1.9.3", "v1.10.0"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_compare_str_ignore_case() {
+        let spec = SortSpec {
+            ignore_case: true,
+            ..Default::default()
+        };
+        assert_eq!(spec.compare_str("b", "A"), Ordering::Greater);
+        assert_eq!(spec.compare_str("a", "B"), Ordering::Less);
+
+        let spec = SortSpec::default();
+        assert_eq!(spec.compare_str("a", "B"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_sort_key_from_str() {
+        assert_eq!("time".parse::<SortKey>(), Ok(SortKey::Mtime));
+        assert_eq!("btime".parse::<SortKey>(), Ok(SortKey::Btime));
+        assert_eq!("e".parse::<SortKey>(), Ok(SortKey::Ext));
+        assert!("tiem".parse::<SortKey>().is_err());
+    }
 }
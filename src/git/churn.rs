@@ -0,0 +1,216 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use git2::{DiffOptions, Patch, Repository, Sort};
+use serde::Serialize;
+
+use super::open_workdir;
+
+/// ## Summary
+/// 履歴の中でファイル・ディレクトリが変更された回数と行数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Churn {
+    /// 変更したコミットの数。ディレクトリでは配下を変更したコミットを1回と数える
+    pub commits: u64,
+    pub additions: u64,
+    pub deletions: u64,
+}
+
+impl Churn {
+    pub fn lines(&self) -> u64 {
+        self.additions + self.deletions
+    }
+}
+
+/// ## Summary
+/// `path`配下のファイル・ディレクトリごとの変更回数と行数を求める
+/// 履歴を1度だけ新しい順に辿り、マージコミットは数えない
+/// キーはリポジトリのワークツリーを起点にした絶対パス
+///
+/// ## Parameters
+/// - `since`: これより古いコミットは数えない(UNIX時間)
+/// - `rev`: 履歴を辿り始めるリビジョン。Noneの場合は`HEAD`
+///
+/// ## Note
+/// リポジトリの外やコミットがない場合は警告を出して空のHashMapを返す
+pub fn churn(path: &Path, since: Option<i64>, rev: Option<&str>) -> HashMap<PathBuf, Churn> {
+    let Some((repo, workdir, relative)) = open_workdir(path, "git churn") else {
+        return HashMap::new();
+    };
+
+    match walk_history(&repo, &relative, since, rev.unwrap_or("HEAD")) {
+        Ok(churn) => churn
+            .into_iter()
+            .map(|(path, churn)| (workdir.join(path), churn))
+            .collect(),
+        Err(e) => {
+            eprintln!("WARNING: failed to read git churn: {}", e.message());
+            HashMap::new()
+        }
+    }
+}
+
+fn walk_history(
+    repo: &Repository,
+    relative: &Path,
+    since: Option<i64>,
+    rev: &str,
+) -> Result<HashMap<PathBuf, Churn>, git2::Error> {
+    let start = repo.revparse_single(rev)?.peel_to_commit()?;
+    let mut diff_options = DiffOptions::new();
+    if !relative.as_os_str().is_empty() {
+        diff_options.pathspec(relative);
+    }
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TIME)?;
+    revwalk.push(start.id())?;
+
+    let mut churn: HashMap<PathBuf, Churn> = HashMap::new();
+    for id in revwalk {
+        let commit = repo.find_commit(id?)?;
+        // コミットの時刻は親子の順序を保証しないので、古いコミットで打ち切らずに飛ばす
+        if since.is_some_and(|since| commit.time().seconds() < since) {
+            continue;
+        }
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+        let diff = repo.diff_tree_to_tree(
+            parent_tree.as_ref(),
+            Some(&commit.tree()?),
+            Some(&mut diff_options),
+        )?;
+
+        // 1つのコミットで同じディレクトリの複数のファイルを変更しても1回と数える
+        let mut counted = HashSet::new();
+        for (i, delta) in diff.deltas().enumerate() {
+            let file = delta.new_file().path().or(delta.old_file().path());
+            let Some(path) = file else {
+                continue;
+            };
+            let (additions, deletions) = match Patch::from_diff(&diff, i)? {
+                Some(patch) => {
+                    let (_, additions, deletions) = patch.line_stats()?;
+                    (additions as u64, deletions as u64)
+                }
+                // バイナリファイルは行数を数えない
+                None => (0, 0),
+            };
+            for ancestor in path.ancestors() {
+                if !ancestor.starts_with(relative) {
+                    break;
+                }
+                let entry = churn.entry(ancestor.to_path_buf()).or_default();
+                if counted.insert(ancestor.to_path_buf()) {
+                    entry.commits += 1;
+                }
+                entry.additions += additions;
+                entry.deletions += deletions;
+            }
+        }
+    }
+    Ok(churn)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use git2::{Commit, Signature, Time};
+
+    use super::*;
+
+    fn commit(repo: &Repository, message: &str, time: i64) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::new("test", "test@example.com", &Time::new(time, 0)).unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    fn repository() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("README.md"), "readme\n").unwrap();
+        fs::write(root.join("src/a.rs"), "a\nb\n").unwrap();
+        fs::write(root.join("src/b.rs"), "b\n").unwrap();
+        commit(&repo, "first", 1000);
+        fs::write(root.join("src/a.rs"), "a\nc\n").unwrap();
+        commit(&repo, "second", 2000);
+        fs::write(root.join("src/a.rs"), "a\nc\nd\n").unwrap();
+        fs::write(root.join("src/b.rs"), "").unwrap();
+        commit(&repo, "third", 3000);
+        dir
+    }
+
+    #[test]
+    fn test_churn() {
+        let dir = repository();
+        let root = dir.path().canonicalize().unwrap();
+        let churn = churn(&root, None, None);
+
+        let a = churn[&root.join("src/a.rs")];
+        assert_eq!(a.commits, 3);
+        assert_eq!((a.additions, a.deletions), (4, 1));
+        assert_eq!(churn[&root.join("src/b.rs")].commits, 2);
+        // ディレクトリは配下を変更したコミットの数
+        let src = churn[&root.join("src")];
+        assert_eq!(src.commits, 3);
+        assert_eq!(src.lines(), 4 + 1 + 1 + 1);
+        assert_eq!(churn[&root].commits, 3);
+        assert_eq!(churn[&root.join("README.md")].commits, 1);
+    }
+
+    #[test]
+    fn test_churn_since() {
+        let dir = repository();
+        let root = dir.path().canonicalize().unwrap();
+        let churn = churn(&root.join("src"), Some(1500), None);
+
+        assert_eq!(churn[&root.join("src/a.rs")].commits, 2);
+        assert_eq!(churn[&root.join("src/b.rs")].commits, 1);
+        assert!(!churn.contains_key(&root.join("README.md")));
+    }
+
+    #[test]
+    fn test_churn_since_skewed_clock() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let repo = Repository::init(&root).unwrap();
+        fs::write(root.join("a.rs"), "a\n").unwrap();
+        fs::write(root.join("b.rs"), "b\n").unwrap();
+        commit(&repo, "first", 1000);
+        fs::write(root.join("a.rs"), "a\nb\n").unwrap();
+        commit(&repo, "second", 2000);
+        // 時計のずれたマシンで作られたコミット
+        fs::write(root.join("b.rs"), "").unwrap();
+        commit(&repo, "skewed", 500);
+        fs::write(root.join("a.rs"), "a\nb\nc\n").unwrap();
+        commit(&repo, "third", 3000);
+        let churn = churn(&root, Some(1500), None);
+
+        assert_eq!(churn[&root.join("a.rs")].commits, 2);
+        assert!(!churn.contains_key(&root.join("b.rs")));
+    }
+}
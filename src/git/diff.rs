@@ -0,0 +1,107 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use git2::{Delta, DiffFindOptions, DiffOptions, Patch, Repository, Tree};
+use serde::Serialize;
+
+/// ## Summary
+/// 2つのリビジョン間での変更の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    /// ファイル・シンボリックリンク・ディレクトリなどの種類の変更
+    TypeChange,
+}
+
+impl DiffStatus {
+    /// ## Summary
+    /// `git diff --name-status`と同じ1文字のマーカー
+    pub fn marker(&self) -> char {
+        match self {
+            DiffStatus::Added => 'A',
+            DiffStatus::Deleted => 'D',
+            DiffStatus::Modified => 'M',
+            DiffStatus::Renamed => 'R',
+            DiffStatus::TypeChange => 'T',
+        }
+    }
+}
+
+/// ## Summary
+/// `--diff`で表示するエントリの変更
+/// ディレクトリには配下の変更行数を合計したものが入る
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GitDiff {
+    pub status: DiffStatus,
+    /// リネーム前のパス(比較するツリーからの相対パス)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+impl GitDiff {
+    pub fn new(status: DiffStatus) -> Self {
+        Self {
+            status,
+            old_path: None,
+            additions: 0,
+            deletions: 0,
+        }
+    }
+}
+
+/// ## Summary
+/// 2つのツリーを比較してファイルごとの変更を求める
+/// キーは比較するツリーからの相対パスで、削除されたファイルは削除前のパスになる
+///
+/// ## Returns
+/// ファイルごとの変更 or 差分の計算に失敗した場合のError
+pub fn diff_trees(
+    repo: &Repository,
+    old: &Tree,
+    new: &Tree,
+) -> Result<HashMap<PathBuf, GitDiff>, git2::Error> {
+    let mut diff_options = DiffOptions::new();
+    diff_options.include_typechange(true);
+    let mut diff = repo.diff_tree_to_tree(Some(old), Some(new), Some(&mut diff_options))?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    let mut changes = HashMap::new();
+    for (i, delta) in diff.deltas().enumerate() {
+        let status = match delta.status() {
+            Delta::Added | Delta::Untracked | Delta::Copied => DiffStatus::Added,
+            Delta::Deleted => DiffStatus::Deleted,
+            Delta::Renamed => DiffStatus::Renamed,
+            Delta::Typechange => DiffStatus::TypeChange,
+            _ => DiffStatus::Modified,
+        };
+        let file = if status == DiffStatus::Deleted {
+            delta.old_file()
+        } else {
+            delta.new_file()
+        };
+        let Some(path) = file.path() else {
+            continue;
+        };
+
+        let mut change = GitDiff::new(status);
+        if status == DiffStatus::Renamed {
+            change.old_path = delta
+                .old_file()
+                .path()
+                .map(|path| path.to_string_lossy().into_owned());
+        }
+        // バイナリファイルは行数を数えない
+        if let Some(patch) = Patch::from_diff(&diff, i)? {
+            let (_, additions, deletions) = patch.line_stats()?;
+            change.additions = additions;
+            change.deletions = deletions;
+        }
+        changes.insert(path.to_path_buf(), change);
+    }
+    Ok(changes)
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use git2::{Commit, DiffOptions, Repository, Sort, Tree, TreeWalkMode, TreeWalkResult};
+use serde::Serialize;
+
+use crate::utils::datetime::{relative_format, DateTimeWrap};
+
+use super::open_workdir;
+
+/// ## Summary
+/// ファイル・ディレクトリを最後に変更したコミット
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommitInfo {
+    pub id: String,
+    pub short_id: String,
+    pub author: String,
+    /// コミット日時(UNIX時間)
+    pub time: i64,
+    /// コミット日時(RFC 3339)
+    pub date: String,
+    /// コミットメッセージの1行目
+    pub subject: String,
+}
+
+impl CommitInfo {
+    pub fn new(commit: &Commit) -> Self {
+        let id = commit.id().to_string();
+        let time = commit.time().seconds();
+        Self {
+            short_id: id[..7].to_string(),
+            id,
+            author: commit.author().name().unwrap_or_default().to_string(),
+            time,
+            date: DateTimeWrap::from(time).to_rfc3339(),
+            subject: commit.summary().unwrap_or_default().to_string(),
+        }
+    }
+
+    /// ## Summary
+    /// `abc1234 author, 3 days ago: subject`の形式で表示する
+    pub fn summary_line(&self) -> String {
+        format!(
+            "{} {}, {}: {}",
+            self.short_id,
+            self.author,
+            relative_format(self.time, Utc::now().timestamp()),
+            self.subject
+        )
+    }
+}
+
+/// ## Summary
+/// `path`配下のファイル・ディレクトリごとに最後に変更したコミットを求める
+/// 履歴を1度だけ新しい順に辿り、各コミットと親との差分に現れたパスに割り当てる
+/// マージコミットにはすべての親と内容が異なるパスだけを割り当て、`git log -1 -- <path>`と同じ結果にする
+/// `rev`の時点のツリーにあるパスがすべて見つかった時点で打ち切る
+/// キーはリポジトリのワークツリーを起点にした絶対パス
+///
+/// ## Parameters
+/// - `rev`: 履歴を辿り始めるリビジョン。Noneの場合は`HEAD`
+///
+/// ## Note
+/// リポジトリの外やコミットがない場合は警告を出して空のHashMapを返す
+pub fn last_commits(path: &Path, rev: Option<&str>) -> HashMap<PathBuf, CommitInfo> {
+    let Some((repo, workdir, relative)) = open_workdir(path, "git log") else {
+        return HashMap::new();
+    };
+
+    match walk_history(&repo, &relative, rev.unwrap_or("HEAD")) {
+        Ok(commits) => commits
+            .into_iter()
+            .map(|(path, commit)| (workdir.join(path), commit))
+            .collect(),
+        Err(e) => {
+            eprintln!("WARNING: failed to read git log: {}", e.message());
+            HashMap::new()
+        }
+    }
+}
+
+/// ## Returns
+/// `relative`からの相対パスではなく、ワークツリーからの相対パスをキーにしたHashMap
+fn walk_history(
+    repo: &Repository,
+    relative: &Path,
+    rev: &str,
+) -> Result<HashMap<PathBuf, CommitInfo>, git2::Error> {
+    let start = repo.revparse_single(rev)?.peel_to_commit()?;
+
+    // 割り当てが済んでいないパス。開始時点のツリーにあるものだけを探す
+    let mut remaining = HashSet::from([relative.to_path_buf()]);
+    start.tree()?.walk(TreeWalkMode::PreOrder, |root, entry| {
+        let path = Path::new(root).join(String::from_utf8_lossy(entry.name_bytes()).as_ref());
+        if path.starts_with(relative) {
+            remaining.insert(path);
+        }
+        TreeWalkResult::Ok
+    })?;
+
+    let mut diff_options = DiffOptions::new();
+    if !relative.as_os_str().is_empty() {
+        diff_options.pathspec(relative);
+    }
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(start.id())?;
+
+    let mut commits = HashMap::new();
+    for id in revwalk {
+        if remaining.is_empty() {
+            break;
+        }
+        let commit = repo.find_commit(id?)?;
+        let tree = commit.tree()?;
+        let mut parents = commit.parents();
+        let first_parent = parents.next().map(|parent| parent.tree()).transpose()?;
+        let mut changed = changed_paths(repo, first_parent.as_ref(), &tree, &mut diff_options)?;
+        // gitの履歴の単純化と同じく、マージではいずれかの親と同じ内容のパスは親の側で変更されたものとする
+        for parent in parents {
+            if changed.is_empty() {
+                break;
+            }
+            let other = changed_paths(repo, Some(&parent.tree()?), &tree, &mut diff_options)?;
+            changed.retain(|path| other.contains(path));
+        }
+
+        let mut info = None;
+        for path in changed {
+            if remaining.remove(&path) {
+                let info = info.get_or_insert_with(|| CommitInfo::new(&commit));
+                commits.insert(path, info.clone());
+            }
+        }
+    }
+    Ok(commits)
+}
+
+/// ## Summary
+/// 2つのツリーの差分に現れたパスと、その祖先のディレクトリを求める
+/// 変更されたファイルの祖先のディレクトリも同じコミットで変更されている
+fn changed_paths(
+    repo: &Repository,
+    parent: Option<&Tree>,
+    tree: &Tree,
+    diff_options: &mut DiffOptions,
+) -> Result<HashSet<PathBuf>, git2::Error> {
+    let diff = repo.diff_tree_to_tree(parent, Some(tree), Some(diff_options))?;
+    let mut paths = HashSet::new();
+    for delta in diff.deltas() {
+        for file in [delta.new_file(), delta.old_file()] {
+            let Some(path) = file.path() else {
+                continue;
+            };
+            for ancestor in path.ancestors() {
+                if !paths.insert(ancestor.to_path_buf()) {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use git2::{Oid, Signature};
+
+    use super::*;
+
+    fn commit(repo: &Repository, message: &str) -> Oid {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    fn repository() -> (tempfile::TempDir, Oid, Oid) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let repo = Repository::init(&root).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("README.md"), "readme").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        let first = commit(&repo, "first\n\nbody");
+        fs::write(root.join("src/main.rs"), "fn main() { todo!() }").unwrap();
+        let second = commit(&repo, "second");
+        (dir, first, second)
+    }
+
+    #[test]
+    fn test_last_commits() {
+        let (dir, first, second) = repository();
+        let root = dir.path().canonicalize().unwrap();
+        let commits = last_commits(&root, None);
+
+        let id = |path: &str| commits[&root.join(path)].id.clone();
+        assert_eq!(id("README.md"), first.to_string());
+        assert_eq!(id("src/lib.rs"), first.to_string());
+        assert_eq!(id("src/main.rs"), second.to_string());
+        assert_eq!(id("src"), second.to_string());
+        assert_eq!(commits[&root].id, second.to_string());
+
+        let readme = &commits[&root.join("README.md")];
+        assert_eq!(readme.subject, "first");
+        assert_eq!(readme.author, "test");
+        assert_eq!(readme.short_id, first.to_string()[..7]);
+    }
+
+    #[test]
+    fn test_last_commits_in_subdirectory_and_rev() {
+        let (dir, first, _) = repository();
+        let root = dir.path().canonicalize().unwrap();
+
+        let commits = last_commits(&root.join("src"), Some("HEAD~1"));
+        assert_eq!(commits.len(), 3);
+        assert_eq!(commits[&root.join("src/main.rs")].id, first.to_string());
+        assert!(!commits.contains_key(&root.join("README.md")));
+    }
+
+    fn commit_with_parents(repo: &Repository, message: &str, parents: &[Oid]) -> Oid {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let parents: Vec<Commit> = parents
+            .iter()
+            .map(|id| repo.find_commit(*id).unwrap())
+            .collect();
+        let parents: Vec<&Commit> = parents.iter().collect();
+        repo.commit(None, &signature, &signature, message, &tree, &parents)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_merged_changes_belong_to_side_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let repo = Repository::init(&root).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("README.md"), "readme").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        let base = commit_with_parents(&repo, "base", &[]);
+
+        fs::write(root.join("src/main.rs"), "fn main() { side() }").unwrap();
+        let side = commit_with_parents(&repo, "side", &[base]);
+
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("README.md"), "readme on main").unwrap();
+        let main = commit_with_parents(&repo, "main", &[base]);
+
+        fs::write(root.join("src/main.rs"), "fn main() { side() }").unwrap();
+        let merge = commit_with_parents(&repo, "merge", &[main, side]);
+        repo.branch("merged", &repo.find_commit(merge).unwrap(), true)
+            .unwrap();
+        repo.set_head("refs/heads/merged").unwrap();
+
+        let commits = last_commits(&root, None);
+        let id = |path: &str| commits[&root.join(path)].id.clone();
+
+        // マージで取り込んだ変更は、実際に変更したコミットに割り当てる
+        assert_eq!(id("src/main.rs"), side.to_string());
+        assert_eq!(id("src"), side.to_string());
+        assert_eq!(id("README.md"), main.to_string());
+        // どちらの親とも内容が異なるのはマージコミットだけ
+        assert_eq!(commits[&root].id, merge.to_string());
+    }
+
+    #[test]
+    fn test_outside_repository_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(last_commits(dir.path(), None).is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+pub mod churn;
+pub mod diff;
+pub mod log;
+pub mod object;
+pub mod status;
+pub mod submodule;
+
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use git2::{Repository, RepositoryOpenFlags};
+
+/// ## Summary
+/// `path`を含むリポジトリを開く
+/// gitと同じく`GIT_DIR`・`GIT_WORK_TREE`・`GIT_CEILING_DIRECTORIES`が設定されていればそれに従う
+pub fn open_repository(path: &Path) -> Result<Repository, git2::Error> {
+    Repository::open_ext(path, RepositoryOpenFlags::FROM_ENV, &[] as &[&OsStr])
+}
+
+/// ## Summary
+/// `path`を含むリポジトリと、ワークツリー・ワークツリーから`path`への相対パスを求める
+/// 履歴を読む機能で共通の前処理
+///
+/// ## Parameters
+/// - `feature`: 警告に表示する機能名
+///
+/// ## Returns
+/// リポジトリの外やbareリポジトリでは警告を出してNone
+pub(crate) fn open_workdir(path: &Path, feature: &str) -> Option<(Repository, PathBuf, PathBuf)> {
+    let repo = match open_repository(path) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("WARNING: {} is unavailable: {}", feature, e.message());
+            return None;
+        }
+    };
+    let Some(workdir) = repo.workdir() else {
+        eprintln!(
+            "WARNING: {} is unavailable for bare repository {}",
+            feature,
+            repo.path().display()
+        );
+        return None;
+    };
+    let workdir = workdir
+        .canonicalize()
+        .unwrap_or_else(|_| workdir.to_path_buf());
+    let relative = path
+        .canonicalize()
+        .ok()
+        .and_then(|path| path.strip_prefix(&workdir).ok().map(Path::to_path_buf))
+        .unwrap_or_default();
+    Some((repo, workdir, relative))
+}
@@ -0,0 +1,84 @@
+use git2::{ObjectType, Repository, TreeEntry};
+use serde::Serialize;
+
+const MODE_EXECUTABLE: i32 = 0o100755;
+const MODE_SYMLINK: i32 = 0o120000;
+
+/// ## Summary
+/// gitのツリーに記録されたエントリの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitObjectKind {
+    Tree,
+    Blob,
+    /// 実行権限付きのblob
+    Executable,
+    Symlink,
+    /// サブモジュール(gitlink)
+    Submodule,
+}
+
+/// ## Summary
+/// `--rev`で表示するgitオブジェクトの情報
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GitObject {
+    pub kind: GitObjectKind,
+    /// `100644`のような8進数のファイルモード
+    pub mode: String,
+    pub id: String,
+    /// シンボリックリンクのリンク先
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+}
+
+impl GitObject {
+    /// ## Summary
+    /// ツリーオブジェクトそのもの(`--rev`のルート)の情報を作る
+    pub fn tree(id: git2::Oid) -> Self {
+        Self {
+            kind: GitObjectKind::Tree,
+            mode: "040000".to_string(),
+            id: id.to_string(),
+            target: None,
+        }
+    }
+
+    /// ## Summary
+    /// ツリーのエントリから情報を作る
+    /// シンボリックリンクはblobの中身をリンク先として読む
+    pub fn from_entry(repo: &Repository, entry: &TreeEntry) -> Self {
+        let mode = entry.filemode();
+        let kind = match entry.kind() {
+            Some(ObjectType::Tree) => GitObjectKind::Tree,
+            Some(ObjectType::Commit) => GitObjectKind::Submodule,
+            _ if mode == MODE_SYMLINK => GitObjectKind::Symlink,
+            _ if mode == MODE_EXECUTABLE => GitObjectKind::Executable,
+            _ => GitObjectKind::Blob,
+        };
+        let target = if kind == GitObjectKind::Symlink {
+            repo.find_blob(entry.id())
+                .map(|blob| String::from_utf8_lossy(blob.content()).into_owned())
+                .map_err(|e| eprintln!("ERROR: {}", e.message()))
+                .ok()
+        } else {
+            None
+        };
+
+        Self {
+            kind,
+            mode: format!("{:06o}", mode),
+            id: entry.id().to_string(),
+            target,
+        }
+    }
+
+    /// ## Summary
+    /// 名前の後ろに付ける表示 (シンボリックリンクのリンク先・サブモジュールのコミット)
+    pub fn suffix(&self) -> String {
+        match self.kind {
+            GitObjectKind::Symlink => format!(" -> {}", self.target.as_deref().unwrap_or("")),
+            GitObjectKind::Submodule => format!(" @ {}", &self.id[..7]),
+            _ => String::new(),
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{builder::PossibleValue, ValueEnum};
+use git2::Status;
+use serde::Serialize;
+
+use super::open_repository;
+
+/// ## Summary
+/// `git status --porcelain`と同じ2桁の状態
+/// 1桁目がインデックス(ステージ済み)、2桁目がワークツリーの変更を表す
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct GitStatus {
+    pub index: char,
+    pub worktree: char,
+}
+
+impl GitStatus {
+    pub const CLEAN: Self = Self::new(' ', ' ');
+    pub const UNTRACKED: Self = Self::new('?', '?');
+    pub const IGNORED: Self = Self::new('!', '!');
+    pub const CONFLICTED: Self = Self::new('U', 'U');
+
+    pub const fn new(index: char, worktree: char) -> Self {
+        Self { index, worktree }
+    }
+
+    /// ## Summary
+    /// ステータスを2文字のマーカーにする (例: `M `, ` M`, `??`)
+    pub fn marker(&self) -> String {
+        format!("{}{}", self.index, self.worktree)
+    }
+
+    pub fn is_clean(&self) -> bool {
+        *self == Self::CLEAN
+    }
+
+    pub fn is_untracked(&self) -> bool {
+        *self == Self::UNTRACKED
+    }
+
+    pub fn is_ignored(&self) -> bool {
+        *self == Self::IGNORED
+    }
+
+    pub fn is_conflicted(&self) -> bool {
+        *self == Self::CONFLICTED
+    }
+
+    /// ## Summary
+    /// ステージ済みの変更があるか
+    pub fn is_staged(&self) -> bool {
+        !matches!(self.index, ' ' | '?' | '!' | 'U')
+    }
+
+    /// ## Summary
+    /// ステージされていないワークツリーの変更があるか(未追跡は含まない)
+    pub fn is_modified(&self) -> bool {
+        !matches!(self.worktree, ' ' | '?' | '!' | 'U')
+    }
+
+    /// ## Summary
+    /// `classes`のいずれかに当てはまるか。空の場合は変更があれば当てはまる
+    /// 無視されたファイルはどの種類にも当てはまらない
+    pub fn is_in(&self, classes: &[StatusClass]) -> bool {
+        if self.is_clean() || self.is_ignored() {
+            return false;
+        }
+        if classes.is_empty() {
+            return true;
+        }
+        classes.iter().any(|class| match class {
+            StatusClass::Staged => self.is_staged(),
+            StatusClass::Worktree => self.is_modified(),
+            StatusClass::Untracked => self.is_untracked(),
+            StatusClass::Conflicted => self.is_conflicted(),
+        })
+    }
+
+    /// ## Summary
+    /// ディレクトリに集約するために2つのステータスを合成する
+    /// 優先順位は 衝突 > 桁ごとの変更 > 未追跡 で、結果は常に`git status --porcelain`に現れる組み合わせになる
+    /// - 衝突を含む場合は`UU`
+    /// - 桁ごとに同じ変更ならそのまま、異なる変更が混在する場合は`M`
+    /// - 未追跡は他の変更と混在する場合はワークツリーの`M`として扱う
+    /// - `D`はもう一方の桁が空の場合だけ残し、そうでなければ`M`にする
+    ///
+    /// 無視されたファイルは集約しない
+    pub fn merge(self, other: Self) -> Self {
+        if self.is_clean() || self.is_ignored() {
+            return other;
+        }
+        if other.is_clean() || other.is_ignored() || self == other {
+            return self;
+        }
+        if self.is_conflicted() || other.is_conflicted() {
+            return Self::CONFLICTED;
+        }
+
+        let columns = |status: Self| {
+            if status.is_untracked() {
+                (' ', 'M')
+            } else {
+                (status.index, status.worktree)
+            }
+        };
+        let merge_column = |a: char, b: char| match (a, b) {
+            (' ', c) | (c, ' ') => c,
+            (a, b) if a == b => a,
+            _ => 'M',
+        };
+        let (self_index, self_worktree) = columns(self);
+        let (other_index, other_worktree) = columns(other);
+        let mut index = merge_column(self_index, other_index);
+        let mut worktree = merge_column(self_worktree, other_worktree);
+        // `D `・` D`・` R`は単独でしか現れない
+        if index == 'D' && worktree != ' ' {
+            index = 'M';
+        }
+        if matches!(worktree, 'R' | 'C') && index != ' ' {
+            worktree = 'M';
+        }
+        Self::new(index, worktree)
+    }
+}
+
+/// ## Summary
+/// `--git-changed`で表示する変更の種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusClass {
+    /// インデックスにステージ済みの変更
+    Staged,
+    /// ステージされていないワークツリーの変更
+    Worktree,
+    Untracked,
+    Conflicted,
+}
+
+impl ValueEnum for StatusClass {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::Staged,
+            Self::Worktree,
+            Self::Untracked,
+            Self::Conflicted,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            StatusClass::Staged => Some(PossibleValue::new("staged").alias("index")),
+            StatusClass::Worktree => Some(PossibleValue::new("worktree").alias("modified")),
+            StatusClass::Untracked => Some(PossibleValue::new("untracked")),
+            StatusClass::Conflicted => Some(PossibleValue::new("conflicted")),
+        }
+    }
+}
+
+impl FromStr for StatusClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "staged" | "index" => Ok(StatusClass::Staged),
+            "worktree" | "modified" => Ok(StatusClass::Worktree),
+            "untracked" => Ok(StatusClass::Untracked),
+            "conflicted" => Ok(StatusClass::Conflicted),
+            _ => Err(format!("Invalid status class:{}", s)),
+        }
+    }
+}
+
+impl From<Status> for GitStatus {
+    fn from(status: Status) -> Self {
+        if status.is_conflicted() {
+            return Self::CONFLICTED;
+        }
+        if status.is_ignored() {
+            return Self::IGNORED;
+        }
+        let index = if status.is_index_new() {
+            'A'
+        } else if status.is_index_modified() {
+            'M'
+        } else if status.is_index_deleted() {
+            'D'
+        } else if status.is_index_renamed() {
+            'R'
+        } else if status.is_index_typechange() {
+            'T'
+        } else {
+            ' '
+        };
+        // ステージで削除してワークツリーに残したファイルはINDEX_DELETEDとWT_NEWが同時に立つ
+        // porcelainの`D `と`??`のうち、ステージされた変更を優先する
+        if status.is_wt_new() && index == ' ' {
+            return Self::UNTRACKED;
+        }
+        let worktree = if status.is_wt_modified() {
+            'M'
+        } else if status.is_wt_deleted() {
+            'D'
+        } else if status.is_wt_renamed() {
+            'R'
+        } else if status.is_wt_typechange() {
+            'T'
+        } else {
+            ' '
+        };
+        Self::new(index, worktree)
+    }
+}
+
+impl fmt::Display for GitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.index, self.worktree)
+    }
+}
+
+/// ## Summary
+/// `path`を含むリポジトリのgit statusを取得する
+/// キーはリポジトリのワークツリーを起点にした絶対パス
+/// ディレクトリには配下のファイルのステータスを集約したものが入る
+///
+/// ## Parameters
+/// - `include_ignored`: 無視されたファイルも`!!`として含める。
+///   無視されたディレクトリは中を辿らずディレクトリだけが入る
+///
+/// ## Note
+/// リポジトリの外やbareリポジトリでは警告を出して空のHashMapを返す
+pub fn get_git_statuses(path: &Path, include_ignored: bool) -> HashMap<PathBuf, GitStatus> {
+    let repo = match open_repository(path) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("WARNING: git status is unavailable: {}", e.message());
+            return HashMap::new();
+        }
+    };
+    let Some(workdir) = repo.workdir() else {
+        eprintln!(
+            "WARNING: git status is unavailable for bare repository {}",
+            repo.path().display()
+        );
+        return HashMap::new();
+    };
+    let workdir = workdir
+        .canonicalize()
+        .unwrap_or_else(|_| workdir.to_path_buf());
+
+    let mut status_options = git2::StatusOptions::new();
+    status_options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(include_ignored)
+        .recurse_ignored_dirs(false)
+        .renames_head_to_index(true);
+    let git_statuses = match repo.statuses(Some(&mut status_options)) {
+        Ok(statuses) => statuses,
+        Err(e) => {
+            eprintln!("WARNING: failed to get git statuses: {}", e.message());
+            return HashMap::new();
+        }
+    };
+
+    let mut statuses = HashMap::new();
+    for entry in git_statuses.iter() {
+        // リネームではpath()が元のパスを返すので、porcelainと同じく新しいパスに付ける
+        let renamed_to = entry
+            .head_to_index()
+            .filter(|_| entry.status().is_index_renamed())
+            .and_then(|delta| delta.new_file().path().map(Path::to_path_buf));
+        let path = match (renamed_to, entry.path()) {
+            (Some(renamed_to), _) => workdir.join(renamed_to),
+            (None, Some(path)) => workdir.join(path.trim_end_matches('/')),
+            (None, None) => continue,
+        };
+        let status = GitStatus::from(entry.status());
+        if status.is_ignored() {
+            // 無視されたファイルは親ディレクトリの状態に影響しない
+            // 無視されたディレクトリの中に追跡されているファイルの変更があればそちらを優先する
+            statuses.entry(path).or_insert(status);
+            continue;
+        }
+        // 削除されたファイルや走査で除外されたファイルの変更も親ディレクトリに反映されるよう、
+        // 走査とは独立にワークツリーまでの祖先へ集約しておく
+        for ancestor in path.ancestors().skip(1) {
+            if !ancestor.starts_with(&workdir) {
+                break;
+            }
+            let rolled = statuses
+                .entry(ancestor.to_path_buf())
+                .or_insert(GitStatus::CLEAN);
+            *rolled = rolled.merge(status);
+        }
+        statuses.insert(path, status);
+    }
+    statuses.retain(|_, status| !status.is_clean());
+    statuses
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use git2::Repository;
+
+    use super::*;
+
+    fn commit_all(repo: &Repository) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_outside_repository_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(get_git_statuses(dir.path(), false).is_empty());
+    }
+
+    #[test]
+    fn test_bare_repository_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        Repository::init_bare(dir.path()).unwrap();
+        assert!(get_git_statuses(dir.path(), false).is_empty());
+    }
+
+    #[test]
+    fn test_statuses_are_relative_to_workdir() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let repo = Repository::init(&root).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        commit_all(&repo);
+        fs::write(root.join("src/main.rs"), "fn main() { todo!() }").unwrap();
+        fs::write(root.join("src/new.rs"), "").unwrap();
+
+        let statuses = get_git_statuses(&root.join("src"), false);
+
+        assert_eq!(
+            statuses.get(&root.join("src/main.rs")),
+            Some(&GitStatus::new(' ', 'M'))
+        );
+        assert_eq!(
+            statuses.get(&root.join("src/new.rs")),
+            Some(&GitStatus::UNTRACKED)
+        );
+    }
+
+    #[test]
+    fn test_status_markers() {
+        assert_eq!(GitStatus::from(Status::INDEX_MODIFIED).marker(), "M ");
+        assert_eq!(GitStatus::from(Status::WT_MODIFIED).marker(), " M");
+        assert_eq!(GitStatus::from(Status::WT_NEW).marker(), "??");
+        assert_eq!(
+            GitStatus::from(Status::INDEX_DELETED | Status::WT_NEW).marker(),
+            "D "
+        );
+        assert_eq!(GitStatus::from(Status::INDEX_NEW).marker(), "A ");
+        assert_eq!(GitStatus::from(Status::INDEX_RENAMED).marker(), "R ");
+        assert_eq!(GitStatus::from(Status::CONFLICTED).marker(), "UU");
+        assert_eq!(GitStatus::from(Status::IGNORED).marker(), "!!");
+        assert_eq!(
+            GitStatus::from(Status::INDEX_MODIFIED | Status::WT_MODIFIED).marker(),
+            "MM"
+        );
+    }
+
+    #[test]
+    fn test_is_in() {
+        let both = GitStatus::new('M', 'M');
+        assert!(both.is_in(&[]));
+        assert!(both.is_in(&[StatusClass::Staged]));
+        assert!(both.is_in(&[StatusClass::Worktree]));
+        assert!(!both.is_in(&[StatusClass::Untracked]));
+        assert!(GitStatus::UNTRACKED.is_in(&[StatusClass::Untracked]));
+        assert!(!GitStatus::UNTRACKED.is_in(&[StatusClass::Staged, StatusClass::Worktree]));
+        assert!(!GitStatus::CONFLICTED.is_in(&[StatusClass::Staged]));
+        assert!(!GitStatus::IGNORED.is_in(&[]));
+        assert!(!GitStatus::CLEAN.is_in(&[]));
+    }
+
+    #[test]
+    fn test_merge() {
+        let staged = GitStatus::new('M', ' ');
+        let modified = GitStatus::new(' ', 'M');
+        assert_eq!(GitStatus::CLEAN.merge(staged), staged);
+        assert_eq!(staged.merge(modified).marker(), "MM");
+        assert_eq!(GitStatus::new('A', ' ').merge(staged).marker(), "M ");
+        assert_eq!(
+            GitStatus::UNTRACKED.merge(GitStatus::UNTRACKED).marker(),
+            "??"
+        );
+        assert_eq!(GitStatus::UNTRACKED.merge(modified).marker(), " M");
+        assert_eq!(
+            GitStatus::new('A', ' ')
+                .merge(GitStatus::UNTRACKED)
+                .marker(),
+            "AM"
+        );
+        assert_eq!(GitStatus::new('D', ' ').merge(modified).marker(), "MM");
+        assert_eq!(GitStatus::IGNORED.merge(staged), staged);
+        assert_eq!(staged.merge(GitStatus::CONFLICTED), GitStatus::CONFLICTED);
+    }
+
+    /// ## Summary
+    /// `git status --porcelain`に現れる組み合わせか
+    fn is_porcelain(status: GitStatus) -> bool {
+        match (status.index, status.worktree) {
+            ('?', '?') | ('!', '!') | ('U', 'U') => true,
+            ('D', ' ') | (' ', 'R' | 'C') => true,
+            (' ', y) => matches!(y, 'M' | 'T' | 'D'),
+            (x, y) => {
+                matches!(x, 'M' | 'T' | 'A' | 'R' | 'C') && matches!(y, ' ' | 'M' | 'T' | 'D')
+            }
+        }
+    }
+
+    #[test]
+    fn test_merge_yields_porcelain_pairs() {
+        let statuses: Vec<GitStatus> = [
+            "M ", " M", "MM", "A ", "AM", "D ", " D", "R ", "RM", "T ", " T", "??", "!!", "UU",
+        ]
+        .iter()
+        .map(|marker| {
+            let mut chars = marker.chars();
+            GitStatus::new(chars.next().unwrap(), chars.next().unwrap())
+        })
+        .collect();
+
+        for a in &statuses {
+            assert!(is_porcelain(*a), "{}", a);
+            for b in &statuses {
+                let merged = a.merge(*b);
+                assert!(is_porcelain(merged), "{} + {} = {}", a, b, merged);
+                // 集約の結果は順序によらない
+                assert_eq!(merged, b.merge(*a), "{} + {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_staged_rename_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let repo = Repository::init(&root).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/old.rs"), "fn renamed() {}\n".repeat(20)).unwrap();
+        commit_all(&repo);
+
+        // git mv src/old.rs src/new.rs
+        fs::rename(root.join("src/old.rs"), root.join("src/new.rs")).unwrap();
+        let mut index = repo.index().unwrap();
+        index.remove_path(Path::new("src/old.rs")).unwrap();
+        index.add_path(Path::new("src/new.rs")).unwrap();
+        index.write().unwrap();
+
+        let statuses = get_git_statuses(&root, false);
+
+        assert_eq!(statuses[&root.join("src/new.rs")].marker(), "R ");
+        assert!(!statuses.contains_key(&root.join("src/old.rs")));
+        assert_eq!(statuses[&root.join("src")].marker(), "R ");
+    }
+
+    #[test]
+    fn test_directories_are_rolled_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let repo = Repository::init(&root).unwrap();
+        fs::create_dir_all(root.join("src/utils")).unwrap();
+        fs::write(root.join("src/utils/mod.rs"), "").unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join("README.md"), "").unwrap();
+        commit_all(&repo);
+        fs::remove_file(root.join("src/utils/mod.rs")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/guide.md"), "").unwrap();
+
+        let statuses = get_git_statuses(&root, false);
+
+        assert_eq!(statuses[&root.join("src/utils")].marker(), " D");
+        assert_eq!(statuses[&root.join("src")].marker(), " D");
+        assert_eq!(statuses[&root.join("docs")], GitStatus::UNTRACKED);
+        assert_eq!(statuses[&root].marker(), " M");
+        assert!(!statuses.contains_key(&root.join("src/lib.rs")));
+        assert!(!statuses.contains_key(&root.join("README.md")));
+    }
+}
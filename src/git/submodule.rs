@@ -0,0 +1,323 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use git2::{Repository, StatusOptions, SubmoduleIgnore, SubmoduleStatus};
+use serde::Serialize;
+
+use super::{
+    open_repository,
+    status::{get_git_statuses, GitStatus},
+};
+
+/// ## Summary
+/// ツリーの途中にある別のリポジトリの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoundaryKind {
+    Submodule,
+    /// `git worktree add`で作られた作業ツリー
+    Worktree,
+    /// 親のリポジトリとは独立したリポジトリ
+    Nested,
+}
+
+/// ## Summary
+/// サブモジュール・作業ツリー・入れ子のリポジトリのルート
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RepoBoundary {
+    pub kind: BoundaryKind,
+    /// チェックアウトされているコミット。未初期化のサブモジュールでは親が記録しているコミット
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// コミットされていない変更があるか
+    pub dirty: bool,
+    /// サブモジュールがチェックアウトされているか
+    pub initialized: bool,
+}
+
+impl fmt::Display for RepoBoundary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            BoundaryKind::Submodule => "submodule",
+            BoundaryKind::Worktree => "worktree",
+            BoundaryKind::Nested => "repo",
+        };
+        write!(f, "[{}", kind)?;
+        if let Some(commit) = &self.commit {
+            write!(f, " @ {}", &commit[..commit.len().min(7)])?;
+        }
+        if !self.initialized {
+            write!(f, ", uninitialized")?;
+        } else if self.dirty {
+            write!(f, ", dirty")?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// ## Summary
+/// `path`を含むリポジトリのサブモジュールを求める
+/// キーはリポジトリのワークツリーを起点にした絶対パス
+///
+/// ## Note
+/// リポジトリの外やbareリポジトリでは空のHashMapを返す。警告は`get_git_statuses`で出す
+pub fn submodules(path: &Path) -> HashMap<PathBuf, RepoBoundary> {
+    let Ok(repo) = open_repository(path) else {
+        return HashMap::new();
+    };
+    let Some(workdir) = repo.workdir() else {
+        return HashMap::new();
+    };
+    let workdir = workdir
+        .canonicalize()
+        .unwrap_or_else(|_| workdir.to_path_buf());
+    let submodules = match repo.submodules() {
+        Ok(submodules) => submodules,
+        Err(e) => {
+            eprintln!("WARNING: failed to read submodules: {}", e.message());
+            return HashMap::new();
+        }
+    };
+
+    submodules
+        .iter()
+        .map(|submodule| {
+            let status = submodule
+                .name()
+                .and_then(|name| repo.submodule_status(name, SubmoduleIgnore::None).ok());
+            let initialized = status.is_some_and(|status| !status.is_wd_uninitialized());
+            let dirty = status.is_some_and(|status| {
+                status.is_wd_modified()
+                    || status.contains(SubmoduleStatus::WD_INDEX_MODIFIED)
+                    || status.is_wd_wd_modified()
+                    || status.is_wd_untracked()
+            });
+            let commit = submodule
+                .workdir_id()
+                .or_else(|| submodule.index_id())
+                .or_else(|| submodule.head_id())
+                .map(|id| id.to_string());
+            let boundary = RepoBoundary {
+                kind: BoundaryKind::Submodule,
+                commit,
+                dirty,
+                initialized,
+            };
+            (workdir.join(submodule.path()), boundary)
+        })
+        .collect()
+}
+
+/// ## Summary
+/// サブモジュールの中のgit statusを再帰的に求める
+/// キーはそれぞれのサブモジュールのワークツリーを起点にした絶対パス
+///
+/// ## Note
+/// サブモジュールのルート自体は含めない
+/// ステージされたポインターの変更など、ルートの状態は親のリポジトリが記録しているものを使う
+pub fn submodule_statuses(path: &Path) -> HashMap<PathBuf, GitStatus> {
+    let Ok(repo) = open_repository(path) else {
+        return HashMap::new();
+    };
+    let Ok(submodules) = repo.submodules() else {
+        return HashMap::new();
+    };
+
+    let mut statuses = HashMap::new();
+    for submodule in submodules {
+        // 未初期化のサブモジュールは開けないので飛ばす
+        let Ok(sub_repo) = submodule.open() else {
+            continue;
+        };
+        let Some(workdir) = sub_repo.workdir() else {
+            continue;
+        };
+        let mut sub_statuses = get_git_statuses(workdir, false);
+        sub_statuses.remove(
+            &workdir
+                .canonicalize()
+                .unwrap_or_else(|_| workdir.to_path_buf()),
+        );
+        statuses.extend(sub_statuses);
+        statuses.extend(submodule_statuses(workdir));
+    }
+    statuses
+}
+
+/// ## Summary
+/// `path`が親とは別のリポジトリのルートであれば、その情報を返す
+/// サブモジュールは`submodules`で求めるので、呼び出し側で除外しておく
+pub fn nested_repository(path: &Path) -> Option<RepoBoundary> {
+    if !path.join(".git").exists() {
+        return None;
+    }
+    let repo = Repository::open(path).ok()?;
+    let kind = if repo.is_worktree() {
+        BoundaryKind::Worktree
+    } else {
+        BoundaryKind::Nested
+    };
+    let commit = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .map(|id| id.to_string());
+    let mut status_options = StatusOptions::new();
+    status_options.include_untracked(true);
+    let dirty = repo
+        .statuses(Some(&mut status_options))
+        .is_ok_and(|statuses| !statuses.is_empty());
+
+    Some(RepoBoundary {
+        kind,
+        commit,
+        dirty,
+        initialized: true,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use git2::{Commit, Signature};
+
+    use super::*;
+    use crate::{build_tree, tree::TreeNode, TreeOptions};
+
+    fn commit_all(repo: &Repository) -> git2::Oid {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "commit",
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    fn child<'a>(node: &'a TreeNode, name: &str) -> &'a TreeNode {
+        node.get_children()
+            .iter()
+            .flatten()
+            .find(|child| child.get_name() == name)
+            .unwrap()
+    }
+
+    /// ## Summary
+    /// `vendor/lib`にサブモジュール、`tools`に入れ子のリポジトリを持つリポジトリ
+    /// サブモジュールの中のファイルは変更しておく
+    fn repository() -> (tempfile::TempDir, tempfile::TempDir, git2::Oid) {
+        let upstream_dir = tempfile::tempdir().unwrap();
+        let upstream = Repository::init(upstream_dir.path()).unwrap();
+        fs::write(upstream_dir.path().join("lib.rs"), "pub fn lib() {}").unwrap();
+        let upstream_head = commit_all(&upstream);
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        commit_all(&repo);
+        let url = upstream_dir.path().to_str().unwrap();
+        let mut submodule = repo.submodule(url, Path::new("vendor/lib"), true).unwrap();
+        submodule.clone(None).unwrap();
+        submodule.add_finalize().unwrap();
+        commit_all(&repo);
+
+        fs::create_dir_all(root.join("tools")).unwrap();
+        let nested = Repository::init(root.join("tools")).unwrap();
+        fs::write(root.join("tools/run.sh"), "").unwrap();
+        commit_all(&nested);
+
+        fs::write(root.join("vendor/lib/lib.rs"), "pub fn lib() { todo!() }").unwrap();
+        (dir, upstream_dir, upstream_head)
+    }
+
+    #[test]
+    fn test_submodules() {
+        let (dir, _upstream, head) = repository();
+        let root = dir.path().canonicalize().unwrap();
+        let submodules = submodules(&root);
+
+        let submodule = &submodules[&root.join("vendor/lib")];
+        assert_eq!(submodule.kind, BoundaryKind::Submodule);
+        assert_eq!(submodule.commit, Some(head.to_string()));
+        assert!(submodule.initialized);
+        assert!(submodule.dirty);
+        assert_eq!(
+            submodule.to_string(),
+            format!("[submodule @ {}, dirty]", &head.to_string()[..7])
+        );
+    }
+
+    #[test]
+    fn test_boundaries_in_tree() {
+        let (dir, _upstream, _) = repository();
+        let options = TreeOptions::new(dir.path()).git_integration(true);
+        let root = build_tree(&options).unwrap();
+
+        let lib = child(child(&root, "vendor"), "lib");
+        assert_eq!(lib.get_repository().unwrap().kind, BoundaryKind::Submodule);
+        assert_eq!(child(lib, "lib.rs").get_git_status(), None);
+        let tools = child(&root, "tools");
+        assert_eq!(tools.get_repository().unwrap().kind, BoundaryKind::Nested);
+        assert!(!tools.get_repository().unwrap().dirty);
+        assert_eq!(child(&root, "main.rs").get_repository(), None);
+    }
+
+    #[test]
+    fn test_recurse_submodules() {
+        let (dir, _upstream, _) = repository();
+        let options = TreeOptions::new(dir.path()).recurse_submodules(true);
+        let root = build_tree(&options).unwrap();
+
+        let lib = child(child(&root, "vendor"), "lib");
+        assert_eq!(
+            child(lib, "lib.rs").get_git_status(),
+            Some(GitStatus::new(' ', 'M'))
+        );
+    }
+
+    #[test]
+    fn test_staged_submodule_pointer() {
+        let (dir, _upstream, _) = repository();
+        let root = dir.path();
+        // サブモジュールのコミットを進めてポインターの変更をステージし、サブモジュールの中も変更しておく
+        let sub_repo = Repository::open(root.join("vendor/lib")).unwrap();
+        commit_all(&sub_repo);
+        let mut repo = Repository::open(root).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("vendor/lib")).unwrap();
+        index.write().unwrap();
+        fs::write(root.join("vendor/lib/new.rs"), "").unwrap();
+
+        let options = TreeOptions::new(root).recurse_submodules(true);
+        let tree = build_tree(&options).unwrap();
+        let lib = child(child(&tree, "vendor"), "lib");
+        assert_eq!(lib.get_git_status(), Some(GitStatus::new('M', 'M')));
+        assert_eq!(
+            child(lib, "new.rs").get_git_status(),
+            Some(GitStatus::UNTRACKED)
+        );
+
+        // 親がサブモジュールの中の変更を無視する設定なら、ステージされたポインターの変更だけが残る
+        repo.submodule_set_ignore("vendor/lib", SubmoduleIgnore::Dirty)
+            .unwrap();
+        let tree = build_tree(&options).unwrap();
+        let lib = child(child(&tree, "vendor"), "lib");
+        assert_eq!(lib.get_git_status(), Some(GitStatus::new('M', ' ')));
+    }
+}
@@ -0,0 +1,397 @@
+use serde::Serialize;
+
+use crate::tree::TreeNode;
+
+/// ## Summary
+/// HTMLに埋め込むノード
+/// 表示に必要な値だけを持ち、ブラウザ側で並べ替えられるようにバイト数は数値で持つ
+#[derive(Debug, Serialize)]
+struct HtmlNode<'a> {
+    name: &'a str,
+    dir: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git: Option<String>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    suffix: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commit: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<HtmlNode<'a>>,
+}
+
+impl<'a> HtmlNode<'a> {
+    fn new(node: &'a TreeNode) -> Self {
+        let git = match (node.get_diff(), node.get_git_status()) {
+            (Some(diff), _) => Some(diff.status.marker().to_string()),
+            (None, Some(status)) => Some(status.to_string()),
+            (None, None) => None,
+        };
+        Self {
+            name: node.get_name(),
+            dir: node.is_dir(),
+            bytes: node.get_bytes(),
+            size: node.get_size().as_ref().map(ToString::to_string),
+            git,
+            suffix: node.suffix().trim_start().to_string(),
+            commit: node.get_last_commit().map(|commit| commit.summary_line()),
+            children: node
+                .get_children()
+                .iter()
+                .flatten()
+                .map(HtmlNode::new)
+                .collect(),
+        }
+    }
+}
+
+/// ## Summary
+/// ツリーを1ファイルで完結するHTMLに変換する
+/// 外部のスクリプトやスタイルを読み込まないので、CIの成果物としてそのまま開ける
+///
+/// ## Returns
+/// 折りたたみ・絞り込み・並べ替えができるHTML
+pub fn tree_to_html(node: &TreeNode) -> String {
+    let data = serde_json::to_string(&HtmlNode::new(node)).unwrap_or_else(|_| "null".to_string());
+    fill_template(
+        TEMPLATE,
+        &[
+            ("title", &escape_html(node.get_name())),
+            ("data", &escape_script(&data)),
+        ],
+    )
+}
+
+/// ## Summary
+/// テンプレートの`{{key}}`を1回の走査で値に置き換える
+/// 置き換えた値の中に`{{...}}`が含まれていても、さらに置き換えることはない
+fn fill_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut html = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        html.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after.find("}}").and_then(|end| {
+            values
+                .iter()
+                .find(|(key, _)| *key == &after[..end])
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                html.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                html.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    html.push_str(rest);
+    html
+}
+
+/// ## Summary
+/// HTMLの本文・属性に埋め込む文字列をエスケープする
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// ## Summary
+/// `<script>`に埋め込むJSONをエスケープする
+/// ファイル名に`</script>`や`<!--`が含まれていてもスクリプトが途中で終わらないようにする
+fn escape_script(json: &str) -> String {
+    json.replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+}
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{title}}</title>
+<style>
+body { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: 13px; margin: 1.5em; color: #24292f; }
+h1 { font-size: 16px; }
+#controls { margin-bottom: 1em; display: flex; gap: .5em; }
+#controls input { flex: 1; max-width: 30em; padding: .3em; font: inherit; }
+table { border-collapse: collapse; }
+th { text-align: left; border-bottom: 1px solid #d0d7de; padding: .3em 1em .3em 0; cursor: pointer; user-select: none; white-space: nowrap; }
+th.sorted::after { content: " \25B4"; }
+th.sorted.desc::after { content: " \25BE"; }
+td { padding: .1em 1em .1em 0; white-space: nowrap; vertical-align: top; }
+td.num { text-align: right; }
+.toggle { display: inline-block; width: 1.2em; cursor: pointer; color: #57606a; }
+.dir { color: #0550ae; font-weight: 600; }
+.suffix, .commit { color: #57606a; }
+.git { font-weight: 600; }
+.git-A { color: #1a7f37; }
+.git-M { color: #9a6700; }
+.git-D, .git-conflicted { color: #cf222e; }
+.git-R { color: #0969da; }
+.git-ignored { color: #8c959f; }
+mark { background: #fff8c5; }
+</style>
+</head>
+<body>
+<h1>{{title}}</h1>
+<div id="controls">
+<input id="filter" type="search" placeholder="Filter by name" autofocus>
+<button id="expand" type="button">Expand all</button>
+<button id="collapse" type="button">Collapse all</button>
+</div>
+<table>
+<thead><tr id="header"></tr></thead>
+<tbody id="rows"></tbody>
+</table>
+<script id="tree-data" type="application/json">{{data}}</script>
+<script>
+(function () {
+  "use strict";
+  var root = JSON.parse(document.getElementById("tree-data").textContent);
+  var columns = [{ key: "name", label: "Name" }];
+  var has = { bytes: false, git: false, commit: false };
+  var state = { sort: "name", desc: false, filter: "" };
+
+  (function prepare(node, depth) {
+    node.depth = depth;
+    node.open = depth < 2;
+    node.children = node.children || [];
+    if (node.bytes !== undefined) has.bytes = true;
+    if (node.git) has.git = true;
+    if (node.commit) has.commit = true;
+    node.children.forEach(function (child) { prepare(child, depth + 1); });
+  })(root, 0);
+  if (has.bytes) columns.push({ key: "bytes", label: "Size" });
+  if (has.git) columns.push({ key: "git", label: "Git" });
+  if (has.commit) columns.push({ key: "commit", label: "Last commit" });
+
+  function compare(a, b) {
+    var x = a[state.sort], y = b[state.sort], result;
+    if (state.sort === "bytes") {
+      result = (x || 0) - (y || 0);
+    } else {
+      result = String(x || "").localeCompare(String(y || ""), undefined, { numeric: true });
+    }
+    if (result === 0 && state.sort !== "name") result = a.name.localeCompare(b.name);
+    return state.desc ? -result : result;
+  }
+
+  function matches(node) {
+    var query = state.filter;
+    node.match = !query || node.name.toLowerCase().indexOf(query) >= 0;
+    node.visibleChild = false;
+    node.children.forEach(function (child) {
+      if (matches(child)) node.visibleChild = true;
+    });
+    return node.match || node.visibleChild;
+  }
+
+  function highlight(name) {
+    var text = document.createDocumentFragment();
+    var index = state.filter ? name.toLowerCase().indexOf(state.filter) : -1;
+    if (index < 0) {
+      text.appendChild(document.createTextNode(name));
+      return text;
+    }
+    var mark = document.createElement("mark");
+    mark.textContent = name.substr(index, state.filter.length);
+    text.appendChild(document.createTextNode(name.substr(0, index)));
+    text.appendChild(mark);
+    text.appendChild(document.createTextNode(name.substr(index + state.filter.length)));
+    return text;
+  }
+
+  function cell(row, className) {
+    var td = document.createElement("td");
+    if (className) td.className = className;
+    row.appendChild(td);
+    return td;
+  }
+
+  function gitClass(git) {
+    if (git === "!!") return "git-ignored";
+    if (git === "??") return "git-A";
+    if (git.indexOf("U") >= 0 || git === "AA" || git === "DD") return "git-conflicted";
+    var marker = git.trim().charAt(0);
+    return "git-" + marker;
+  }
+
+  function render() {
+    var tbody = document.getElementById("rows");
+    var fragment = document.createDocumentFragment();
+    matches(root);
+    (function add(node) {
+      var row = document.createElement("tr");
+      var name = cell(row);
+      name.style.paddingLeft = node.depth * 1.4 + "em";
+      var toggle = document.createElement("span");
+      toggle.className = "toggle";
+      var open = node.open || (state.filter && node.visibleChild);
+      if (node.children.length) {
+        toggle.textContent = open ? "▾" : "▸";
+        toggle.addEventListener("click", function () {
+          node.open = !open;
+          render();
+        });
+      }
+      name.appendChild(toggle);
+      var label = document.createElement("span");
+      label.className = node.dir ? "dir" : "file";
+      label.appendChild(highlight(node.name));
+      name.appendChild(label);
+      if (node.suffix) {
+        var suffix = document.createElement("span");
+        suffix.className = "suffix";
+        suffix.textContent = " " + node.suffix;
+        name.appendChild(suffix);
+      }
+      if (has.bytes) {
+        var size = cell(row, "num");
+        size.textContent = node.size || "";
+        if (node.bytes !== undefined) size.title = node.bytes + " bytes";
+      }
+      if (has.git) {
+        var git = cell(row, "git");
+        if (node.git) {
+          git.textContent = node.git;
+          git.className += " " + gitClass(node.git);
+        }
+      }
+      if (has.commit) cell(row, "commit").textContent = node.commit || "";
+      fragment.appendChild(row);
+      if (open) {
+        node.children
+          .filter(function (child) { return child.match || child.visibleChild; })
+          .sort(compare)
+          .forEach(add);
+      }
+    })(root);
+    tbody.replaceChildren(fragment);
+  }
+
+  var header = document.getElementById("header");
+  columns.forEach(function (column) {
+    var th = document.createElement("th");
+    th.textContent = column.label;
+    th.addEventListener("click", function () {
+      state.desc = state.sort === column.key ? !state.desc : column.key === "bytes";
+      state.sort = column.key;
+      Array.prototype.forEach.call(header.children, function (other) {
+        other.className = "";
+      });
+      th.className = "sorted" + (state.desc ? " desc" : "");
+      render();
+    });
+    if (column.key === state.sort) th.className = "sorted";
+    header.appendChild(th);
+  });
+
+  function setOpen(node, open) {
+    node.open = open || node.depth === 0;
+    node.children.forEach(function (child) { setOpen(child, open); });
+  }
+  document.getElementById("expand").addEventListener("click", function () {
+    setOpen(root, true);
+    render();
+  });
+  document.getElementById("collapse").addEventListener("click", function () {
+    setOpen(root, false);
+    render();
+  });
+  document.getElementById("filter").addEventListener("input", function (event) {
+    state.filter = event.target.value.trim().toLowerCase();
+    render();
+  });
+
+  render();
+})();
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{build_tree, SizeFormat, TreeOptions};
+
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.path().join("<!--<script>&.txt"), "").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_tree_to_html() {
+        let dir = fixture();
+        let options = TreeOptions::new(dir.path())
+            .size(SizeFormat::Bytes)
+            .gitignore(false);
+        let root = build_tree(&options).unwrap();
+        let html = tree_to_html(&root);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(r#""name":"main.rs""#));
+        assert!(html.contains(r#""bytes":12"#));
+        // 外部のリソースを読み込まない
+        assert!(!html.contains("src=\"http"));
+        assert!(!html.contains("<link"));
+    }
+
+    #[test]
+    fn test_file_names_cannot_close_script() {
+        let dir = fixture();
+        let root = build_tree(&TreeOptions::new(dir.path()).gitignore(false)).unwrap();
+        let html = tree_to_html(&root);
+
+        assert_eq!(html.matches("<script").count(), 2);
+        assert!(!html.contains("<!--"));
+        assert!(html.contains(r#"\u003c!--\u003cscript\u003e\u0026.txt"#));
+    }
+
+    #[test]
+    fn test_placeholders_in_names_are_not_filled() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("{{data}}");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("{{title}}"), "").unwrap();
+        let html = tree_to_html(&build_tree(&TreeOptions::new(&root).gitignore(false)).unwrap());
+
+        assert!(html.contains("<title>{{data}}</title>"));
+        assert!(html.contains("<h1>{{data}}</h1>"));
+        assert!(html.contains(r#""name":"{{title}}""#));
+        assert_eq!(
+            html.matches("tree-data").count(),
+            TEMPLATE.matches("tree-data").count()
+        );
+    }
+
+    #[test]
+    fn test_fill_template() {
+        assert_eq!(
+            fill_template("{{a}}-{{b}}-{{c}}-{{", &[("a", "{{b}}"), ("b", "2")]),
+            "{{b}}-2-{{c}}-{{"
+        );
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">&'"#),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#39;"
+        );
+    }
+}
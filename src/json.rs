@@ -0,0 +1,434 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    foramt::json_style::JsonStyle,
+    git::{
+        churn::Churn, diff::GitDiff, log::CommitInfo, object::GitObject, submodule::RepoBoundary,
+    },
+    tree::TreeNode,
+    utils::{
+        datetime::DateTimeWrap,
+        files::{format_permissions, Symlink},
+    },
+};
+
+/// ## Summary
+/// `--json-style native`の形式のバージョン
+/// 互換性のない変更をした場合に上げる
+pub const NATIVE_SCHEMA_VERSION: u32 = 1;
+
+/// ## Summary
+/// ツリーを指定した形式のJSONに変換する
+///
+/// ## Parameters
+/// - `root`: 走査を開始したパス。`--json-style tree`ではGNU treeと同じく指定されたままルートの名前にする
+///
+/// ## Returns
+/// 整形したJSON or シリアライズに失敗した場合のError
+pub fn tree_to_json(node: &TreeNode, style: JsonStyle, root: &Path) -> Result<String> {
+    let json = match style {
+        JsonStyle::Legacy => serde_json::to_string_pretty(node)?,
+        JsonStyle::Native => serde_json::to_string_pretty(&NativeDocument {
+            version: NATIVE_SCHEMA_VERSION,
+            root: NativeNode::new(node, ".".to_string()),
+        })?,
+        JsonStyle::Tree => {
+            let mut report = GnuReport::default();
+            let name = root.to_string_lossy();
+            let root = GnuEntry::new(node, &name, &mut report);
+            // GNU treeと同じくルートはディレクトリの数に含めない
+            report.directories = report.directories.saturating_sub(1);
+            serde_json::to_string_pretty(&[root, GnuEntry::Report(report)])?
+        }
+    };
+    Ok(json)
+}
+
+#[derive(Serialize)]
+struct NativeDocument<'a> {
+    version: u32,
+    root: NativeNode<'a>,
+}
+
+/// ## Summary
+/// `--json-style native`の1ノード
+/// ディレクトリは空でも`children`を持ち、ファイルは持たない
+#[derive(Serialize)]
+pub(crate) struct NativeNode<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    name: &'a str,
+    /// ルートからの相対パス。区切り文字は常に`/`
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<u32>,
+    /// UNIX時間(秒)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accessed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    changed: Option<i64>,
+    /// porcelain形式の2文字の状態
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_status: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    ignored: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_object: Option<&'a GitObject>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diff: Option<&'a GitDiff>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_commit: Option<&'a CommitInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    churn: Option<Churn>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repository: Option<&'a RepoBoundary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symlink: Option<&'a Symlink>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    mount_point: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<NativeNode<'a>>>,
+}
+
+impl<'a> NativeNode<'a> {
+    fn new(node: &'a TreeNode, path: String) -> Self {
+        let children = node.is_dir().then(|| {
+            node.get_children()
+                .iter()
+                .flatten()
+                .map(|child| {
+                    let path = if path == "." {
+                        child.get_name().to_string()
+                    } else {
+                        format!("{}/{}", path, child.get_name())
+                    };
+                    NativeNode::new(child, path)
+                })
+                .collect()
+        });
+        Self {
+            children,
+            ..Self::entry(node, path)
+        }
+    }
+
+    /// ## Summary
+    /// 子を含めずに1ノードだけを変換する
+    pub(crate) fn entry(node: &'a TreeNode, path: String) -> Self {
+        let kind = if node.get_symlink().is_some() {
+            "symlink"
+        } else if node.is_dir() {
+            "directory"
+        } else {
+            "file"
+        };
+        let verbose = node.get_verbose_info();
+        let timestamps = node.get_timestamps();
+        let seconds = |time: Option<&DateTimeWrap>| time.map(|time| time.timestamp());
+
+        Self {
+            kind,
+            name: node.get_name(),
+            path,
+            size: node.get_bytes(),
+            #[cfg(unix)]
+            owner: verbose.map(|info| info.owner),
+            #[cfg(not(unix))]
+            owner: None,
+            #[cfg(unix)]
+            group: verbose.map(|info| info.group),
+            #[cfg(not(unix))]
+            group: None,
+            #[cfg(unix)]
+            mode: verbose.map(|info| info.mode),
+            #[cfg(not(unix))]
+            mode: None,
+            // unixの詳細情報の`created`はctime(inodeの変更日時)なので、作成日時には使わない
+            #[cfg(unix)]
+            created: seconds(timestamps.born.as_ref()),
+            #[cfg(not(unix))]
+            created: seconds(
+                timestamps
+                    .born
+                    .as_ref()
+                    .or(verbose.map(|info| &info.created)),
+            ),
+            modified: seconds(
+                timestamps
+                    .modified
+                    .as_ref()
+                    .or(verbose.map(|info| &info.modified)),
+            ),
+            accessed: seconds(timestamps.accessed.as_ref()),
+            #[cfg(unix)]
+            changed: seconds(
+                timestamps
+                    .changed
+                    .as_ref()
+                    .or(verbose.map(|info| &info.created)),
+            ),
+            #[cfg(not(unix))]
+            changed: seconds(timestamps.changed.as_ref()),
+            git_status: node.get_git_status().map(|status| status.to_string()),
+            ignored: node.is_ignored(),
+            git_object: node.get_git_object(),
+            diff: node.get_diff(),
+            last_commit: node.get_last_commit(),
+            churn: node.get_churn(),
+            repository: node.get_repository(),
+            symlink: node.get_symlink(),
+            mount_point: node.is_mount_point(),
+            children: None,
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
+struct GnuReport {
+    directories: u64,
+    files: u64,
+}
+
+/// ## Summary
+/// GNU treeの`-J`の1要素
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum GnuEntry<'a> {
+    Directory {
+        name: &'a str,
+        #[serde(flatten)]
+        info: GnuInfo,
+        contents: Vec<GnuEntry<'a>>,
+    },
+    Link {
+        name: &'a str,
+        target: &'a str,
+        #[serde(flatten)]
+        info: GnuInfo,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        contents: Vec<GnuEntry<'a>>,
+    },
+    File {
+        name: &'a str,
+        #[serde(flatten)]
+        info: GnuInfo,
+    },
+    Report(GnuReport),
+}
+
+/// ## Summary
+/// `-s`・`-l`で追加される値
+/// GNU treeと同じく`mode`は8進数の文字列、`prot`は`ls -l`形式
+#[derive(Serialize)]
+struct GnuInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prot: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+}
+
+impl<'a> GnuEntry<'a> {
+    fn new(node: &'a TreeNode, name: &'a str, report: &mut GnuReport) -> Self {
+        if node.is_dir() {
+            report.directories += 1;
+        } else {
+            report.files += 1;
+        }
+        let contents = node
+            .get_children()
+            .iter()
+            .flatten()
+            .map(|child| GnuEntry::new(child, child.get_name(), report))
+            .collect();
+        let info = GnuInfo::new(node);
+
+        match node.get_symlink() {
+            Some(symlink) => GnuEntry::Link {
+                name,
+                target: &symlink.target,
+                info,
+                contents,
+            },
+            None if node.is_dir() => GnuEntry::Directory {
+                name,
+                info,
+                contents,
+            },
+            None => GnuEntry::File { name, info },
+        }
+    }
+}
+
+impl GnuInfo {
+    #[cfg(unix)]
+    fn new(node: &TreeNode) -> Self {
+        let mode = node.get_verbose_info().map(|info| info.mode);
+        Self {
+            mode: mode.map(|mode| format!("{:04o}", mode & 0o7777)),
+            prot: mode.map(format_permissions),
+            size: node.get_bytes(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn new(node: &TreeNode) -> Self {
+        Self {
+            mode: None,
+            prot: None,
+            size: node.get_bytes(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{build_tree, SizeFormat, TreeOptions};
+
+    fn fixture() -> (tempfile::TempDir, TreeNode) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/empty")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("a.txt"), "ab").unwrap();
+        let options = TreeOptions::new(dir.path())
+            .size(SizeFormat::HumanReadable)
+            .gitignore(false);
+        let node = build_tree(&options).unwrap();
+        (dir, node)
+    }
+
+    fn parse(node: &TreeNode, style: JsonStyle) -> Value {
+        serde_json::from_str(&tree_to_json(node, style, Path::new(".")).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_legacy_json_is_unchanged() {
+        let (_dir, node) = fixture();
+        assert_eq!(
+            parse(&node, JsonStyle::Legacy),
+            serde_json::to_value(&node).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_native_json() {
+        let (_dir, node) = fixture();
+        let json = parse(&node, JsonStyle::Native);
+
+        assert_eq!(json["version"], NATIVE_SCHEMA_VERSION);
+        let root = &json["root"];
+        assert_eq!(root["type"], "directory");
+        assert_eq!(root["path"], ".");
+        assert_eq!(root["size"], 14);
+        assert_eq!(root["children"][0]["type"], "file");
+        assert_eq!(root["children"][0]["path"], "a.txt");
+        assert!(root["children"][0].get("children").is_none());
+        let src = &root["children"][1];
+        // 空のディレクトリとファイルを区別できる
+        assert_eq!(
+            src["children"][0],
+            json!({"type": "directory", "name": "empty", "path": "src/empty", "size": 0, "children": []})
+        );
+        assert_eq!(src["children"][1]["path"], "src/main.rs");
+        assert_eq!(src["children"][1]["size"], 12);
+    }
+
+    #[test]
+    fn test_native_json_timestamps_are_numbers() {
+        let (dir, _) = fixture();
+        let options = TreeOptions::new(dir.path())
+            .sort_keys([crate::SortKey::Mtime])
+            .gitignore(false);
+        let node = build_tree(&options).unwrap();
+        let json = parse(&node, JsonStyle::Native);
+
+        assert!(json["root"]["children"][0]["modified"].is_i64());
+    }
+
+    #[test]
+    fn test_gnu_tree_json() {
+        let (_dir, node) = fixture();
+        let json = parse(&node, JsonStyle::Tree);
+
+        // ルートの名前はGNU treeと同じく指定されたパスのまま
+        assert_eq!(
+            json,
+            json!([
+                {"type": "directory", "name": ".", "size": 14, "contents": [
+                    {"type": "file", "name": "a.txt", "size": 2},
+                    {"type": "directory", "name": "src", "size": 12, "contents": [
+                        {"type": "directory", "name": "empty", "size": 0, "contents": []},
+                        {"type": "file", "name": "main.rs", "size": 12}
+                    ]}
+                ]},
+                {"type": "report", "directories": 2, "files": 2}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_gnu_tree_json_root_name_is_path_as_given() {
+        let (_dir, node) = fixture();
+        let json: Value = serde_json::from_str(
+            &tree_to_json(&node, JsonStyle::Tree, Path::new("./src/")).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(json[0]["name"], "./src/");
+        assert_eq!(json[0]["contents"][0]["name"], "a.txt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_native_json_does_not_publish_ctime_as_created() {
+        let (dir, _) = fixture();
+        let options = TreeOptions::new(dir.path()).verbose(true).gitignore(false);
+        let node = build_tree(&options).unwrap();
+        let json = parse(&node, JsonStyle::Native);
+
+        let file = &json["root"]["children"][0];
+        assert!(file.get("created").is_none());
+        assert!(file["changed"].is_i64());
+        assert!(file["modified"].is_i64());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_gnu_tree_json_mode() {
+        let (dir, _) = fixture();
+        std::os::unix::fs::symlink("a.txt", dir.path().join("link")).unwrap();
+        fs::set_permissions(
+            dir.path().join("a.txt"),
+            std::os::unix::fs::PermissionsExt::from_mode(0o644),
+        )
+        .unwrap();
+        let options = TreeOptions::new(dir.path()).verbose(true).gitignore(false);
+        let node = build_tree(&options).unwrap();
+        let json = parse(&node, JsonStyle::Tree);
+
+        let contents = &json[0]["contents"];
+        assert_eq!(contents[0]["mode"], "0644");
+        assert_eq!(contents[0]["prot"], "-rw-r--r--");
+        assert_eq!(contents[1]["type"], "link");
+        assert_eq!(contents[1]["target"], "a.txt");
+    }
+}
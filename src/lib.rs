@@ -0,0 +1,35 @@
+//! # rs-xtree
+//! ディレクトリツリーを構築するライブラリ
+//!
+//! CLI(`rsxtree`)はこのクレートの薄いラッパーです。
+//!
+//! ```no_run
+//! use rs_xtree::{build_tree, print_tree, TreeOptions};
+//!
+//! let options = TreeOptions::new("src").max_depth(2).extensions(["rs"]);
+//! let node = build_tree(&options).unwrap();
+//! print_tree(&node, "", true);
+//! ```
+pub mod constatns;
+pub mod filter;
+pub mod foramt;
+pub mod git;
+pub mod html;
+pub mod json;
+pub mod listing;
+pub mod ndjson;
+pub mod serialize;
+pub mod stats;
+pub mod tree;
+pub mod utils;
+pub mod walker;
+
+pub use foramt::{
+    column::Column, json_style::JsonStyle, mode::Mode, sizeformat::SizeFormat, sort::SortKey,
+};
+pub use html::tree_to_html;
+pub use json::tree_to_json;
+pub use listing::{listing_options, tree_to_table};
+pub use serialize::{tree_to_toml, tree_to_xml, tree_to_yaml};
+pub use tree::{build_tree, print_tree, stream_tree, tree_to_markdown, TreeNode, TreeOptions};
+pub use walker::Walker;
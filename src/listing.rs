@@ -0,0 +1,296 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::{
+    foramt::{column::Column, sizeformat::SizeFormat},
+    git::open_repository,
+    tree::{TreeNode, TreeOptions},
+};
+
+/// ## Summary
+/// 選択した列の出力に必要な情報を集めるようにオプションを変更する
+/// サイズはバイト数で、更新日時・所有者・パーミッションは詳細情報から、git statusはリポジトリから取得する
+///
+/// ## Parameters
+/// - `columns`: 出力する列。空の場合はすべての列
+///
+/// ## Note
+/// 列を指定しなかった場合、git statusはルートがワークツリー内にあるときだけ取得する
+/// リポジトリ外では警告を出さずにgitの列を空にする
+pub fn listing_options(options: TreeOptions, columns: &[Column]) -> TreeOptions {
+    let git = if columns.is_empty() {
+        in_work_tree(&options.path)
+    } else {
+        columns.contains(&Column::Git)
+    };
+    let columns = if columns.is_empty() {
+        &Column::ALL[..]
+    } else {
+        columns
+    };
+    let mut options = options;
+    if columns.contains(&Column::Size) {
+        options = options.size(SizeFormat::Bytes);
+    }
+    if columns
+        .iter()
+        .any(|column| matches!(column, Column::Mtime | Column::Owner | Column::Permissions))
+    {
+        options = options.verbose(true);
+    }
+    if git {
+        options = options.git_integration(true);
+    }
+    options
+}
+
+/// ## Summary
+/// `path`がgitのワークツリー内にあるか。`open_workdir`と違い、見つからなくても警告を出さない
+fn in_work_tree(path: &Path) -> bool {
+    open_repository(path).is_ok_and(|repo| repo.workdir().is_some())
+}
+
+/// ## Summary
+/// ツリーを1エントリ1行の表に平坦化し、CSVまたはTSVにする
+/// 行は親が子より先に来る順に並ぶ。区切り文字・引用符・改行を含む名前は引用符で囲む
+///
+/// ## Parameters
+/// - `columns`: 出力する列。空の場合はすべての列
+/// - `delimiter`: 区切り文字。CSVは`b','`、TSVは`b'\t'`
+///
+/// ## Returns
+/// ヘッダー行付きの表 or 書き込みに失敗した場合のError
+pub fn tree_to_table(node: &TreeNode, columns: &[Column], delimiter: u8) -> Result<String> {
+    let columns = if columns.is_empty() {
+        &Column::ALL[..]
+    } else {
+        columns
+    };
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(vec![]);
+    writer.write_record(columns.iter().map(Column::name))?;
+    write_rows(&mut writer, node, ".", 0, columns, &Owners::default())?;
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+fn write_rows(
+    writer: &mut csv::Writer<Vec<u8>>,
+    node: &TreeNode,
+    path: &str,
+    depth: u32,
+    columns: &[Column],
+    owners: &Owners,
+) -> Result<()> {
+    let record: Vec<String> = columns
+        .iter()
+        .map(|column| cell(node, path, depth, *column, owners))
+        .collect();
+    writer.write_record(&record)?;
+
+    for child in node.get_children().iter().flatten() {
+        let child_path = if depth == 0 {
+            child.get_name().to_string()
+        } else {
+            format!("{}/{}", path, child.get_name())
+        };
+        write_rows(writer, child, &child_path, depth + 1, columns, owners)?;
+    }
+    Ok(())
+}
+
+/// ## Summary
+/// 1つのセルの値。取得していない値は空文字
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn cell(node: &TreeNode, path: &str, depth: u32, column: Column, owners: &Owners) -> String {
+    let verbose = node.get_verbose_info();
+    match column {
+        Column::Path => path.to_string(),
+        Column::Type => if node.get_symlink().is_some() {
+            "symlink"
+        } else if node.is_dir() {
+            "directory"
+        } else {
+            "file"
+        }
+        .to_string(),
+        Column::Depth => depth.to_string(),
+        Column::Size => node
+            .get_bytes()
+            .map(|bytes| bytes.to_string())
+            .unwrap_or_default(),
+        Column::Mtime => node
+            .get_timestamps()
+            .modified
+            .as_ref()
+            .or(verbose.map(|info| &info.modified))
+            .map(|time| time.to_rfc3339())
+            .unwrap_or_default(),
+        #[cfg(unix)]
+        Column::Owner => verbose
+            .map(|info| owners.name(info.owner))
+            .unwrap_or_default(),
+        #[cfg(unix)]
+        Column::Permissions => verbose
+            .map(|info| crate::utils::files::format_permissions(info.mode))
+            .unwrap_or_default(),
+        // Windowsでは所有者とパーミッションを取得しない
+        #[cfg(not(unix))]
+        Column::Owner | Column::Permissions => String::new(),
+        Column::Git => match (node.get_diff(), node.get_git_status()) {
+            (Some(diff), _) => diff.status.marker().to_string(),
+            (None, Some(status)) => status.to_string(),
+            (None, None) => String::new(),
+        },
+    }
+}
+
+/// ## Summary
+/// UIDから所有者名を引く。同じUIDは1度だけ問い合わせる
+#[derive(Default)]
+struct Owners {
+    #[cfg(unix)]
+    users: uzers::UsersCache,
+}
+
+impl Owners {
+    /// ## Returns
+    /// ユーザー名 or 名前を解決できない場合はUID
+    #[cfg(unix)]
+    fn name(&self, uid: u32) -> String {
+        use uzers::Users;
+
+        self.users
+            .get_user_by_uid(uid)
+            .map(|user| user.name().to_string_lossy().into_owned())
+            .unwrap_or_else(|| uid.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+    use crate::build_tree;
+
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("a,\"b\".txt"), "ab").unwrap();
+        dir
+    }
+
+    fn table(dir: &tempfile::TempDir, columns: &[Column], delimiter: u8) -> String {
+        let options = listing_options(TreeOptions::new(dir.path()).gitignore(false), columns);
+        let node = build_tree(&options).unwrap();
+        tree_to_table(&node, columns, delimiter).unwrap()
+    }
+
+    #[test]
+    fn test_csv_columns() {
+        let dir = fixture();
+        let csv = table(
+            &dir,
+            &[Column::Path, Column::Type, Column::Depth, Column::Size],
+            b',',
+        );
+
+        assert_eq!(
+            csv,
+            "path,type,depth,size\n\
+             .,directory,0,14\n\
+             \"a,\"\"b\"\".txt\",file,1,2\n\
+             src,directory,1,12\n\
+             src/main.rs,file,2,12\n"
+        );
+    }
+
+    #[test]
+    fn test_tsv_quoting() {
+        let dir = fixture();
+        fs::write(dir.path().join("tab\there"), "").unwrap();
+        fs::write(dir.path().join("comma,only"), "").unwrap();
+        let tsv = table(&dir, &[Column::Path, Column::Type], b'\t');
+
+        assert!(tsv.starts_with("path\ttype\n"));
+        // TSVではカンマだけを含む名前は引用符で囲まない
+        assert!(tsv.contains("\ncomma,only\tfile\n"));
+        assert!(tsv.contains("\n\"a,\"\"b\"\".txt\"\tfile\n"));
+        assert!(tsv.contains("\n\"tab\there\"\tfile\n"));
+    }
+
+    #[test]
+    fn test_all_columns_by_default() {
+        let dir = fixture();
+        let csv = table(&dir, &[], b',');
+        let mut lines = csv.lines();
+
+        assert_eq!(
+            lines.next(),
+            Some("path,type,depth,size,mtime,owner,permissions,git")
+        );
+        let root: Vec<&str> = lines.next().unwrap().split(',').collect();
+        assert_eq!(root[..4], [".", "directory", "0", "14"]);
+        assert!(!root[4].is_empty());
+    }
+
+    #[test]
+    fn test_git_column_enables_git_status() {
+        let dir = fixture();
+        git2::Repository::init(dir.path()).unwrap();
+
+        for columns in [&[Column::Path, Column::Git][..], &[]] {
+            let csv = table(&dir, columns, b',');
+            let row = csv
+                .lines()
+                .find(|line| line.starts_with("src/main.rs,"))
+                .unwrap();
+            assert!(row.ends_with(",??"), "{}", row);
+        }
+    }
+
+    #[test]
+    fn test_git_column_empty_outside_work_tree() {
+        let dir = fixture();
+        let options = listing_options(TreeOptions::new(dir.path()), &[]);
+        assert!(!options.git_integration);
+
+        let csv = table(&dir, &[], b',');
+        let row = csv
+            .lines()
+            .find(|line| line.starts_with("src/main.rs,"))
+            .unwrap();
+        assert!(row.ends_with(','), "{}", row);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_metadata_columns() {
+        let dir = fixture();
+        let csv = table(
+            &dir,
+            &[
+                Column::Path,
+                Column::Mtime,
+                Column::Owner,
+                Column::Permissions,
+            ],
+            b',',
+        );
+        let row = csv
+            .lines()
+            .find(|line| line.starts_with("src/main.rs"))
+            .unwrap();
+        let cells: Vec<&str> = row.split(',').collect();
+
+        assert!(chrono::DateTime::parse_from_rfc3339(cells[1]).is_ok());
+        let owner = uzers::get_current_username()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| uzers::get_current_uid().to_string());
+        assert_eq!(cells[2], owner);
+        assert!(cells[3].starts_with("-rw"));
+    }
+}
@@ -1,62 +1,74 @@
-use std::{collections::HashMap, path::PathBuf};
-
-use cli::build_cli;
-use foramt::output::OutputFormat;
-use stats::Stats;
-use tree::{
-    build_tree, build_tree_async, build_tree_parallel, get_git_statuses, print_tree,
-    tree_to_markdown, Tree,
+use cli::{build_cli, columns, json_style, output_format, tree_options};
+use rs_xtree::{
+    build_tree, foramt::output::OutputFormat, listing_options, print_tree, stats, stream_tree,
+    tree_to_html, tree_to_json, tree_to_markdown, tree_to_table, tree_to_toml, tree_to_xml,
+    tree_to_yaml,
 };
 
 mod cli;
-pub mod constatns;
-pub mod foramt;
-pub mod stats;
-pub mod tree;
-pub mod utils;
-#[tokio::main]
-async fn main() {
+
+fn main() {
     let matches = build_cli().get_matches();
-    let tree = Tree::new(&matches);
+    let format = output_format(&matches);
+    let columns = columns(&matches);
+    let mut options = tree_options(&matches);
+    if matches!(format, OutputFormat::Csv | OutputFormat::Tsv) {
+        options = listing_options(options, &columns);
+    }
     if cfg!(debug_assertions) {
-        println!("{:?}", &tree);
+        eprintln!("{:?}", &options);
     }
 
-    let root = PathBuf::from(&tree.path);
-    let git_status = if tree.git_intergration {
-        get_git_statuses(&root)
-    } else {
-        HashMap::new()
-    };
-
-    let mut tree_node = match &tree.mode {
-        foramt::mode::Mode::Async => build_tree_async(&root, 1, &tree, &git_status)
-            .await
-            .unwrap(),
-        foramt::mode::Mode::Sync => build_tree(&root, 1, &tree, &git_status).unwrap(),
-        foramt::mode::Mode::Parallel => build_tree_parallel(&root, 1, &tree, &git_status).unwrap(),
-    };
-    if tree.sort.is_some() {
-        tree_node.sort(&tree.sort.unwrap());
+    if matches.get_flag("ndjson") {
+        let stdout = std::io::BufWriter::new(std::io::stdout());
+        if let Err(e) = stream_tree(&options, stdout) {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+        return;
     }
 
-    match tree.output_format {
-        OutputFormat::Json => {
-            println!("{}", serde_json::to_string_pretty(&tree_node).unwrap());
+    let tree_node = match build_tree(&options) {
+        Ok(node) => node,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
         }
+    };
+
+    match format {
+        OutputFormat::Json => print_serialized(
+            tree_to_json(&tree_node, json_style(&matches), options.get_path())
+                .map(|json| json + "\n"),
+        ),
         OutputFormat::Standard => {
             print_tree(&tree_node, "", true);
         }
         OutputFormat::Markdown => {
             println!("{}", tree_to_markdown(&tree_node, 0));
         }
+        OutputFormat::Html => {
+            println!("{}", tree_to_html(&tree_node));
+        }
+        OutputFormat::Yaml => print_serialized(tree_to_yaml(&tree_node)),
+        OutputFormat::Toml => print_serialized(tree_to_toml(&tree_node)),
+        OutputFormat::Xml => print_serialized(tree_to_xml(&tree_node)),
+        OutputFormat::Csv => print_serialized(tree_to_table(&tree_node, &columns, b',')),
+        OutputFormat::Tsv => print_serialized(tree_to_table(&tree_node, &columns, b'\t')),
         OutputFormat::Stats => {
             let mut stats = stats::Stats::empty();
             stats.collect_stats(&tree_node);
             stats.print_stats();
         }
-        _ => {
-            println!("yet")
+    }
+}
+
+fn print_serialized(output: anyhow::Result<String>) {
+    match output {
+        Ok(output) => print!("{}", output),
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            std::process::exit(1);
         }
     }
 }
@@ -0,0 +1,235 @@
+use std::{
+    fmt,
+    io::{self, Write},
+    path::{Component, Path},
+    sync::{Arc, Mutex},
+};
+
+use serde::Serialize;
+
+use crate::{json::NativeNode, tree::TreeNode};
+
+/// ## Summary
+/// `--ndjson`の1行
+/// `--json-style native`のノードに深さを加え、子は含めない
+#[derive(Serialize)]
+struct Record<'a> {
+    /// ルートを0とする深さ
+    depth: u32,
+    #[serde(flatten)]
+    node: NativeNode<'a>,
+}
+
+struct State {
+    writer: Box<dyn Write + Send>,
+    /// 最初に起きた書き込みのError。以降は書き込まない
+    error: Option<io::Error>,
+}
+
+/// ## Summary
+/// 走査しながらエントリを1行ずつJSONで書き出す出力先
+/// 並列・非同期のWalkerから共有するのでMutexで書き込みを直列化する
+///
+/// ## Note
+/// ディレクトリのサイズは子を合計して求めるので、ディレクトリは中身の後に出力される
+#[derive(Clone)]
+pub struct NdjsonSink {
+    state: Arc<Mutex<State>>,
+}
+
+impl NdjsonSink {
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                writer: Box::new(writer),
+                error: None,
+            })),
+        }
+    }
+
+    /// ## Summary
+    /// ノードを1行書き出す
+    ///
+    /// ## Parameters
+    /// - `relative`: ルートからの相対パス
+    /// - `depth`: ルートを1とする走査中の深さ
+    pub fn write(&self, node: &TreeNode, relative: &Path, depth: u32) {
+        let record = Record {
+            depth: depth.saturating_sub(1),
+            node: NativeNode::entry(node, slash_path(relative)),
+        };
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if state.error.is_some() {
+            return;
+        }
+        let result = serde_json::to_writer(&mut state.writer, &record)
+            .map_err(io::Error::from)
+            .and_then(|_| state.writer.write_all(b"\n"));
+        if let Err(e) = result {
+            state.error = Some(e);
+        }
+    }
+
+    /// ## Summary
+    /// 書き出しを終える
+    ///
+    /// ## Returns
+    /// () or 書き込み中に起きたError
+    pub fn finish(&self) -> io::Result<()> {
+        let mut state = self
+            .state
+            .lock()
+            .map_err(|_| io::Error::other("ndjson writer is poisoned"))?;
+        if let Some(e) = state.error.take() {
+            return Err(e);
+        }
+        state.writer.flush()
+    }
+}
+
+impl fmt::Debug for NdjsonSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NdjsonSink").finish_non_exhaustive()
+    }
+}
+
+/// ## Summary
+/// OSによらず`/`区切りの相対パスにする。ルートは`.`
+fn slash_path(relative: &Path) -> String {
+    let parts: Vec<_> = relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect();
+    if parts.is_empty() {
+        ".".to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{stream_tree, Mode, SizeFormat, TreeOptions};
+
+    /// ## Summary
+    /// 書き出した内容をテストから読めるようにする出力先
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn stream(options: &TreeOptions) -> Vec<Value> {
+        let buffer = Buffer::default();
+        stream_tree(options, buffer.clone()).unwrap();
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    fn fixture() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/utils")).unwrap();
+        fs::create_dir_all(dir.path().join("empty")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("src/utils/mod.rs"), "").unwrap();
+        fs::write(dir.path().join("README.md"), "# readme").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_stream_tree() {
+        let dir = fixture();
+        let options = TreeOptions::new(dir.path())
+            .size(SizeFormat::Bytes)
+            .gitignore(false);
+        let records = stream(&options);
+        let paths: Vec<_> = records
+            .iter()
+            .map(|r| r["path"].as_str().unwrap())
+            .collect();
+
+        // ディレクトリは中身の後に出力される
+        assert_eq!(
+            paths,
+            [
+                "README.md",
+                "empty",
+                "src/main.rs",
+                "src/utils/mod.rs",
+                "src/utils",
+                "src",
+                "."
+            ]
+        );
+        assert_eq!(records[0]["type"], "file");
+        assert_eq!(records[0]["depth"], 1);
+        assert_eq!(records[0]["size"], 8);
+        assert_eq!(records[1]["type"], "directory");
+        assert_eq!(records[3]["depth"], 3);
+        assert_eq!(records[6]["depth"], 0);
+        assert_eq!(records[6]["size"], 20);
+        assert!(records.iter().all(|r| r.get("children").is_none()));
+    }
+
+    #[test]
+    fn test_stream_tree_in_all_modes() {
+        let dir = fixture();
+        let sorted = |mode: Mode| {
+            let options = TreeOptions::new(dir.path()).mode(mode).gitignore(false);
+            let mut paths: Vec<String> = stream(&options)
+                .iter()
+                .map(|r| r["path"].as_str().unwrap().to_string())
+                .collect();
+            paths.sort();
+            paths
+        };
+        let expected = sorted(Mode::Sync);
+        assert_eq!(sorted(Mode::Parallel), expected);
+        assert_eq!(sorted(Mode::Async), expected);
+    }
+
+    #[test]
+    fn test_stream_tree_with_prune() {
+        let dir = fixture();
+        let options = TreeOptions::new(dir.path())
+            .extensions(["rs"])
+            .prune(true)
+            .gitignore(false);
+        let paths: Vec<_> = stream(&options)
+            .iter()
+            .map(|r| r["path"].as_str().unwrap().to_string())
+            .collect();
+
+        // 書き出し済みの子があるディレクトリは空として扱わない
+        assert_eq!(
+            paths,
+            ["src/main.rs", "src/utils/mod.rs", "src/utils", "src", "."]
+        );
+    }
+
+    #[test]
+    fn test_slash_path() {
+        assert_eq!(slash_path(Path::new("")), ".");
+        assert_eq!(slash_path(Path::new("src/utils")), "src/utils");
+    }
+}
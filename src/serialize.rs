@@ -0,0 +1,236 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+use crate::tree::TreeNode;
+
+/// ## Summary
+/// ツリーをYAMLに変換する
+/// キーと構造はJSON出力と同じ
+pub fn tree_to_yaml(node: &TreeNode) -> Result<String> {
+    Ok(serde_yaml::to_string(node)?)
+}
+
+/// ## Summary
+/// ツリーをTOMLに変換する
+/// キーと構造はJSON出力と同じだが、TOMLにはnullがないので子のないノードは`children`を持たない
+pub fn tree_to_toml(node: &TreeNode) -> Result<String> {
+    Ok(toml::to_string_pretty(node)?)
+}
+
+/// ## Summary
+/// ツリーを`tree -X`と同じ形式のXMLに変換する
+/// ディレクトリは`directory`、シンボリックリンクは`link`、それ以外は`file`要素になり、
+/// 最後に`report`要素でディレクトリとファイルの数を出力する
+///
+/// ## Note
+/// 要素の属性はJSON出力の値から作るので、JSONに出力される情報はXMLにも出力される
+/// 入れ子になった値は`last_commit_subject`のように`_`でつないだ属性名にする
+pub fn tree_to_xml(node: &TreeNode) -> Result<String> {
+    let value = serde_json::to_value(node)?;
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tree>\n");
+    let mut report = Report::default();
+    write_xml_node(&mut xml, node, &value, 1, &mut report)?;
+    // tree -Xと同じくルートはディレクトリの数に含めない
+    report.directories = report.directories.saturating_sub(1);
+    writeln!(
+        xml,
+        "  <report>\n    <directories>{}</directories>\n    <files>{}</files>\n  </report>\n</tree>",
+        report.directories, report.files
+    )?;
+    Ok(xml)
+}
+
+#[derive(Default)]
+struct Report {
+    directories: u64,
+    files: u64,
+}
+
+fn write_xml_node(
+    xml: &mut String,
+    node: &TreeNode,
+    value: &Value,
+    depth: usize,
+    report: &mut Report,
+) -> Result<()> {
+    let element = if node.get_symlink().is_some() {
+        "link"
+    } else if node.is_dir() {
+        "directory"
+    } else {
+        "file"
+    };
+    if node.is_dir() {
+        report.directories += 1;
+    } else {
+        report.files += 1;
+    }
+
+    let indent = "  ".repeat(depth);
+    write!(xml, "{}<{}", indent, element)?;
+    for (name, attribute) in xml_attributes(node, value) {
+        write!(xml, " {}=\"{}\"", name, escape_xml(&attribute))?;
+    }
+    xml.push('>');
+
+    let children = node.get_children().iter().flatten().zip(
+        value
+            .get("children")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten(),
+    );
+    let mut has_children = false;
+    for (child, child_value) in children {
+        if !has_children {
+            xml.push('\n');
+            has_children = true;
+        }
+        write_xml_node(xml, child, child_value, depth + 1, report)?;
+    }
+    if has_children {
+        xml.push_str(&indent);
+    }
+    writeln!(xml, "</{}>", element)?;
+    Ok(())
+}
+
+/// ## Summary
+/// ノードのJSONの値から属性を作る
+/// `tree -X`に合わせて、シンボリックリンクのリンク先は`target`、サイズはバイト数にする
+fn xml_attributes(node: &TreeNode, value: &Value) -> Vec<(String, String)> {
+    let mut attributes = vec![("name".to_string(), node.get_name().to_string())];
+    if let Some(symlink) = node.get_symlink() {
+        attributes.push(("target".to_string(), symlink.target.clone()));
+        if symlink.broken {
+            attributes.push(("broken".to_string(), "true".to_string()));
+        }
+        if symlink.recursive {
+            attributes.push(("recursive".to_string(), "true".to_string()));
+        }
+    }
+    if let Some(bytes) = node.get_bytes() {
+        attributes.push(("size".to_string(), bytes.to_string()));
+    }
+
+    if let Some(object) = value.as_object() {
+        for (key, value) in object {
+            if matches!(key.as_str(), "name" | "children" | "size" | "symlink") {
+                continue;
+            }
+            flatten_attribute(key, value, &mut attributes);
+        }
+    }
+    attributes
+}
+
+fn flatten_attribute(key: &str, value: &Value, attributes: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => {}
+        Value::Object(object) => flatten_object(key, object, attributes),
+        Value::String(text) => attributes.push((key.to_string(), text.clone())),
+        other => attributes.push((key.to_string(), other.to_string())),
+    }
+}
+
+fn flatten_object(
+    prefix: &str,
+    object: &Map<String, Value>,
+    attributes: &mut Vec<(String, String)>,
+) {
+    for (key, value) in object {
+        flatten_attribute(&format!("{}_{}", prefix, key), value, attributes);
+    }
+}
+
+/// ## Summary
+/// XMLの属性値に埋め込む文字列をエスケープする
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+    use crate::{build_tree, SizeFormat, TreeOptions};
+
+    fn fixture() -> (tempfile::TempDir, TreeNode) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/empty")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("a&b.txt"), "ab").unwrap();
+        let options = TreeOptions::new(dir.path())
+            .size(SizeFormat::Bytes)
+            .gitignore(false);
+        let node = build_tree(&options).unwrap();
+        (dir, node)
+    }
+
+    #[test]
+    fn test_tree_to_yaml() {
+        let (_dir, node) = fixture();
+        let yaml = tree_to_yaml(&node).unwrap();
+        let value: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+
+        assert_eq!(value["children"][1]["name"], "src");
+        assert_eq!(value["children"][1]["children"][1]["name"], "main.rs");
+    }
+
+    #[test]
+    fn test_tree_to_toml() {
+        let (_dir, node) = fixture();
+        let toml = tree_to_toml(&node).unwrap();
+        let value: toml::Value = toml::from_str(&toml).unwrap();
+
+        let src = &value["children"][1];
+        assert_eq!(src["name"].as_str(), Some("src"));
+        assert_eq!(src["children"][1]["name"].as_str(), Some("main.rs"));
+        // 子のないノードはchildrenを持たない
+        assert!(src["children"][0].get("children").is_none());
+    }
+
+    #[test]
+    fn test_tree_to_xml() {
+        let (_dir, node) = fixture();
+        let xml = tree_to_xml(&node).unwrap();
+        let name = node.get_name();
+        let expected = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<tree>
+  <directory name="{name}" size="14">
+    <file name="a&amp;b.txt" size="2"></file>
+    <directory name="src" size="12">
+      <directory name="empty" size="0"></directory>
+      <file name="main.rs" size="12"></file>
+    </directory>
+  </directory>
+  <report>
+    <directories>2</directories>
+    <files>2</files>
+  </report>
+</tree>
+"#
+        );
+        assert_eq!(xml, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_xml_link() {
+        let dir = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink("missing", dir.path().join("dangling")).unwrap();
+        let node = build_tree(&TreeOptions::new(dir.path()).gitignore(false)).unwrap();
+        let xml = tree_to_xml(&node).unwrap();
+
+        assert!(xml.contains(r#"<link name="dangling" target="missing" broken="true"></link>"#));
+        assert!(xml.contains("<files>1</files>"));
+    }
+}
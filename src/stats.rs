@@ -8,6 +8,8 @@ pub struct Stats(HashMap<String, StatsData>);
 pub struct StatsData {
     count: u64,
     size: f64,
+    /// `--git-churn`が有効な場合の変更回数の合計
+    churn: Option<u64>,
 }
 
 impl Display for StatsData {
@@ -42,32 +44,57 @@ impl Stats {
             }
         };
 
-        let entry = self
-            .0
-            .entry(ext.to_string())
-            .or_insert(StatsData::default());
+        let entry = self.0.entry(ext.to_string()).or_default();
         entry.count += 1;
 
         if let Some(size) = &node.get_size() {
             entry.size += size.to_bytes_f64();
         }
+        if let Some(churn) = node.get_churn() {
+            entry.churn = Some(entry.churn.unwrap_or(0) + churn.commits);
+        }
     }
 
     pub fn print_stats(&self) {
-        println!("{}", HEADER);
+        if self.has_churn() {
+            println!("{}", HEADER_WITH_CHURN);
+        } else {
+            println!("{}", HEADER);
+        }
         println!("{}", self);
     }
+
+    /// ## Summary
+    /// 変更回数の列を表示するかどうか
+    fn has_churn(&self) -> bool {
+        self.0.values().any(|data| data.churn.is_some())
+    }
 }
 
 const HEADER: &str = r#"Extension    Count    Total Size
 --------------------------------"#;
 
+const HEADER_WITH_CHURN: &str = r#"Extension    Count    Churn    Total Size
+-----------------------------------------"#;
+
 impl Display for Stats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let body = self
             .0
             .iter()
-            .map(|(k, v)| format!("{:<13}{}", k, v))
+            .map(|(k, v)| {
+                if self.has_churn() {
+                    format!(
+                        "{:<13}{:<9}{:<9}{} Bytes",
+                        k,
+                        v.count,
+                        v.churn.unwrap_or(0),
+                        v.size
+                    )
+                } else {
+                    format!("{:<13}{}", k, v)
+                }
+            })
             .collect::<Vec<_>>()
             .join("\n");
 
@@ -77,7 +104,7 @@ impl Display for Stats {
 
 #[cfg(test)]
 mod test {
-    use crate::{tree::print_tree, utils::size};
+    use crate::{git::churn::Churn, utils::size};
 
     use super::*;
     fn file(name: &str, size: u64) -> TreeNode {
@@ -111,6 +138,31 @@ mod test {
         assert_eq!(stats.0.get(Stats::EMPTY_KEY).unwrap().size, 2048.0);
     }
 
+    #[test]
+    fn test_churn_column() {
+        let mut stats = Stats::empty();
+        let mut hot = file("main.rs", 10);
+        hot.churn = Some(Churn {
+            commits: 5,
+            additions: 10,
+            deletions: 2,
+        });
+        let mut cold = file("lib.rs", 10);
+        cold.churn = Some(Churn {
+            commits: 1,
+            additions: 1,
+            deletions: 0,
+        });
+        stats.collect_stats(&dir("src", vec![hot, cold, file("new.toml", 1)]));
+
+        assert!(stats.has_churn());
+        assert_eq!(stats.0.get("rs").unwrap().churn, Some(6));
+        assert_eq!(stats.0.get("toml").unwrap().churn, None);
+        assert!(stats
+            .to_string()
+            .contains("toml         1        0        1 Bytes"));
+    }
+
     #[test]
     fn test_directory_node_is_ignored() {
         let mut stats = Stats::empty();
@@ -139,6 +191,6 @@ mod test {
         assert_eq!(stats.0.get("toml").unwrap().count, 1);
         assert_eq!(stats.0.get("toml").unwrap().size, 500.);
 
-        assert!(stats.0.get(Stats::EMPTY_KEY).is_none());
+        assert!(!stats.0.contains_key(Stats::EMPTY_KEY));
     }
 }
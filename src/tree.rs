@@ -1,25 +1,35 @@
 use std::{
+    cmp::Ordering,
     collections::HashMap,
-    fs::{self, DirEntry},
+    io::Write,
     path::{Path, PathBuf},
-    str::FromStr,
 };
 
-use async_recursion::async_recursion;
-use clap::ValueEnum;
-use colored::{ColoredString, Colorize};
-use git2::{Repository, Status};
-use rayon::prelude::*;
+use anyhow::{anyhow, Result};
+use colored::{Color, ColoredString, Colorize};
 use serde::Serialize;
 
 use crate::{
-    constatns::{self, STR_EMPTY},
-    foramt::{mode::Mode, output::OutputFormat, sizeformat::SizeFormat, sort::SortKey},
+    constatns::STR_EMPTY,
+    foramt::{
+        mode::Mode,
+        sizeformat::SizeFormat,
+        sort::{SortKey, SortSpec},
+    },
+    git::{
+        churn::Churn,
+        diff::GitDiff,
+        log::CommitInfo,
+        object::GitObject,
+        status::{get_git_statuses, GitStatus, StatusClass},
+        submodule::{submodule_statuses, RepoBoundary},
+    },
+    ndjson::NdjsonSink,
     utils::{
-        self,
-        files::MetaDataInfo,
+        files::{MetaDataInfo, Symlink, Timestamps},
         size::{self, Unit},
     },
+    walker::{walker_for, DiffWalker, Pipeline, RevWalker, Walker},
 };
 
 const TREE_BRANCH: &str = " ├── ";
@@ -27,47 +37,120 @@ const TREE_LAST_BRANCH: &str = " └── ";
 const TREE_VERTICAL: &str = " │   ";
 const TREE_LAST_EMPTY: &str = "     ";
 
-#[derive(Debug)]
-pub struct Tree {
-    pub path: String,
-    extensions: Vec<String>,
-    size: Option<SizeFormat>,
-    ignores: Vec<String>,
-    max_depth: Option<u32>,
-    pub output_format: OutputFormat,
-    pub git_intergration: bool,
-    verbose: bool,
-    pub sort: Option<SortKey>,
-    pub mode: Mode,
-    is_show_hidden: bool,
+/// ## Summary
+/// ツリー構築のオプション
+/// `TreeOptions::new(path)`から各メソッドをチェーンして組み立てる
+///
+/// ## Examples
+///```
+/// use rs_xtree::{SizeFormat, SortKey, TreeOptions};
+///
+/// let options = TreeOptions::new(".")
+///     .ignores(["target"])
+///     .size(SizeFormat::Bytes)
+///     .sort(SortKey::Size);
+///```
+#[derive(Debug, Clone)]
+pub struct TreeOptions {
+    pub(crate) path: PathBuf,
+    pub(crate) extensions: Vec<String>,
+    pub(crate) exclude_extensions: Vec<String>,
+    pub(crate) prune: bool,
+    pub(crate) size: Option<SizeFormat>,
+    pub(crate) ignores: Vec<String>,
+    pub(crate) includes: Vec<String>,
+    pub(crate) regex: bool,
+    pub(crate) max_depth: Option<u32>,
+    pub(crate) follow: bool,
+    pub(crate) one_file_system: bool,
+    pub(crate) git_integration: bool,
+    pub(crate) git_changed: Option<Vec<StatusClass>>,
+    pub(crate) recurse_submodules: bool,
+    pub(crate) show_ignored: bool,
+    pub(crate) rev: Option<String>,
+    pub(crate) diff: Option<(String, String)>,
+    pub(crate) git_log: bool,
+    pub(crate) git_churn: bool,
+    pub(crate) since: Option<i64>,
+    pub(crate) verbose: bool,
+    pub(crate) sort: SortSpec,
+    pub(crate) mode: Mode,
+    pub(crate) jobs: Option<usize>,
+    pub(crate) is_show_hidden: bool,
+    pub(crate) size_filtered: bool,
+    pub(crate) gitignore: Option<bool>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, PartialEq)]
 pub struct TreeNode {
     name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    git_status: Option<String>,
+    git_status: Option<GitStatus>,
     children: Option<Vec<TreeNode>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     size: Option<size::Unit>,
     #[serde(skip_serializing_if = "Option::is_none")]
     vervose_info: Option<MetaDataInfo>,
+    /// `--rev`で表示したgitオブジェクトの情報
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) git_object: Option<GitObject>,
+    /// `--diff`で比較したリビジョン間の変更
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) diff: Option<GitDiff>,
+    /// `--git-log`で表示する最後に変更したコミット
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) last_commit: Option<CommitInfo>,
+    /// `--git-churn`で表示する履歴での変更回数と行数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) churn: Option<Churn>,
+    /// サブモジュール・作業ツリー・入れ子のリポジトリのルート
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) repository: Option<RepoBoundary>,
+    /// gitで無視されているか
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) ignored: bool,
+    /// シンボリックリンクのリンク先
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) symlink: Option<Symlink>,
+    /// `--one-file-system`で中を走査しなかったマウントポイントか
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) mount_point: bool,
+    /// 集計用のバイト数。`size`は表示形式によって丸められるのでこちらで合計する
+    #[serde(skip)]
+    pub(crate) bytes: Option<u64>,
+    /// 空のディレクトリも`children`がNoneになるので種類はこちらで判定する
+    #[serde(skip)]
+    pub(crate) is_dir: bool,
+    /// ソート用のタイムスタンプ
+    #[serde(skip)]
+    pub(crate) timestamps: Timestamps,
 }
 
 impl TreeNode {
     pub fn new(
         name: String,
-        git_status: Option<String>,
+        git_status: Option<GitStatus>,
         children: Option<Vec<TreeNode>>,
         size: Option<size::Unit>,
         verbose_info: Option<MetaDataInfo>,
     ) -> Self {
         Self {
-            name: name,
-            git_status: git_status,
-            children: children,
-            size: size,
+            name,
+            git_status,
+            is_dir: children.is_some(),
+            children,
+            bytes: size.as_ref().map(|s| s.to_bytes_f64() as u64),
+            timestamps: Timestamps::default(),
+            size,
             vervose_info: verbose_info,
+            git_object: None,
+            diff: None,
+            last_commit: None,
+            churn: None,
+            repository: None,
+            ignored: false,
+            symlink: None,
+            mount_point: false,
         }
     }
     /// ## Summary
@@ -93,53 +176,95 @@ impl TreeNode {
         result
     }
 
-    pub fn sort(&mut self, sort_key: &SortKey) {
-        match sort_key {
-            SortKey::Name => self.sort_by_name(),
-            SortKey::Size => self.sort_by_size(),
-            SortKey::Time => self.sort_by_time(),
-            SortKey::Ext => todo!(),
+    /// ## Summary
+    /// 名前の後ろに付ける表示 (シンボリックリンクのリンク先など)
+    pub(crate) fn suffix(&self) -> String {
+        let mut suffix = self
+            .git_object
+            .as_ref()
+            .map(GitObject::suffix)
+            .unwrap_or_default();
+        if let Some(symlink) = &self.symlink {
+            suffix.push_str(&format!(" {}", symlink));
+        }
+        if self.mount_point {
+            suffix.push_str(" [mount point]");
+        }
+        if let Some(repository) = &self.repository {
+            suffix.push_str(&format!(" {}", repository));
         }
+        suffix
     }
 
-    pub fn sort_by_size(&mut self) {
+    /// ## Summary
+    /// 子を再帰的に並べ替える
+    pub fn sort(&mut self, spec: &SortSpec) {
         if let Some(ref mut children) = self.children {
             for child in children.iter_mut() {
-                child.sort_by_size();
+                child.sort(spec);
             }
 
-            children.sort_by(|a, b| match (&a.size, &b.size) {
-                (None, None) => std::cmp::Ordering::Equal,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (Some(sa), Some(sb)) => sa.cmp(&sb),
+            children.sort_by(|a, b| {
+                let dirs_first = if spec.dirs_first {
+                    b.is_dir.cmp(&a.is_dir)
+                } else {
+                    Ordering::Equal
+                };
+                let ordering = spec.keys().iter().fold(Ordering::Equal, |ordering, key| {
+                    ordering.then_with(|| a.compare_by(b, key, spec))
+                });
+                dirs_first.then(if spec.reverse {
+                    ordering.reverse()
+                } else {
+                    ordering
+                })
             });
         }
     }
 
-    fn sort_by_name(&mut self) {
-        if let Some(ref mut children) = self.children {
-            for child in children.iter_mut() {
-                child.sort_by_name();
+    fn compare_by(&self, other: &TreeNode, key: &SortKey, spec: &SortSpec) -> Ordering {
+        match key {
+            SortKey::Name => spec.compare_str(&self.name, &other.name),
+            SortKey::Size => match (&self.bytes, &other.bytes) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(sa), Some(sb)) => sa.cmp(sb),
+            },
+            SortKey::Mtime | SortKey::Atime | SortKey::Ctime | SortKey::Btime => {
+                let kind = key.time_kind().unwrap();
+                match (self.timestamps.get(kind), other.timestamps.get(kind)) {
+                    (None, None) => Ordering::Equal,
+                    (None, Some(_)) => Ordering::Greater,
+                    (Some(_), None) => Ordering::Less,
+                    (Some(ta), Some(tb)) => ta.cmp(tb),
+                }
+            }
+            // 拡張子のないものを先に並べる
+            SortKey::Ext => match (self.extension(), other.extension()) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Less,
+                (Some(_), None) => Ordering::Greater,
+                (Some(ea), Some(eb)) => spec.compare_str(ea, eb),
+            },
+            // 履歴にないものは変更0回として扱う
+            SortKey::Churn => {
+                let ca = self.churn.unwrap_or_default();
+                let cb = other.churn.unwrap_or_default();
+                ca.commits
+                    .cmp(&cb.commits)
+                    .then_with(|| ca.lines().cmp(&cb.lines()))
             }
-
-            children.sort_by(|a, b| a.name.cmp(&b.name));
         }
     }
 
-    fn sort_by_time(&mut self) {
-        if let Some(ref mut children) = self.children {
-            for child in children.iter_mut() {
-                child.sort_by_time();
-            }
-
-            children.sort_by(|a, b| match (&a.vervose_info, &b.vervose_info) {
-                (None, None) => std::cmp::Ordering::Equal,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (Some(av), Some(bv)) => av.created.cmp(&bv.created),
-            });
+    fn extension(&self) -> Option<&str> {
+        if self.is_dir {
+            return None;
         }
+        Path::new(&self.name)
+            .extension()
+            .and_then(|ext| ext.to_str())
     }
 
     pub fn get_children(&self) -> &Option<Vec<TreeNode>> {
@@ -153,144 +278,519 @@ impl TreeNode {
     pub fn get_size(&self) -> &Option<Unit> {
         &self.size
     }
-}
 
-impl Tree {
-    pub fn new(matches: &clap::ArgMatches) -> Self {
-        let path = matches.get_one::<String>("path").unwrap().clone();
-        let exts = matches
-            .get_many::<String>("ext")
-            .map(|vals| vals.cloned().collect())
-            .unwrap_or_default();
-        let ignores = matches
-            .get_many::<String>("ignore")
-            .map(|vals| vals.cloned().collect())
-            .unwrap_or_default();
-        let mut size = matches.get_one::<SizeFormat>("size").cloned();
-
-        let max_depth = matches.get_one::<u32>("depth").copied();
-        let output_format = if matches.get_flag("json") {
-            OutputFormat::Json
-        } else if matches.get_flag("markdown") {
-            OutputFormat::Markdown
-        } else if matches.get_flag("stats") {
-            OutputFormat::Stats
-        } else {
-            OutputFormat::Standard
-        };
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
 
-        let git_intergration = matches.get_flag("git");
+    pub fn get_bytes(&self) -> Option<u64> {
+        self.bytes
+    }
 
-        let mut is_verbose = matches.get_flag("long");
-        let mode = matches.get_one::<Mode>("mode").cloned().unwrap();
+    pub fn get_git_status(&self) -> Option<GitStatus> {
+        self.git_status
+    }
 
-        // TODO sortによってフラグをtrueにする処理無理矢理感があるので修正
-        let sort = matches.get_one::<SortKey>("sort").cloned();
-        if let Some(sortkey) = &sort {
-            match sortkey {
-                SortKey::Size => {
-                    if size.is_none() {
-                        size = Some(SizeFormat::Bytes);
-                    }
-                }
-                SortKey::Time => {
-                    if !is_verbose {
-                        is_verbose = true;
-                    }
-                }
-                _ => {}
-            }
-        }
+    pub fn get_git_object(&self) -> Option<&GitObject> {
+        self.git_object.as_ref()
+    }
+
+    pub fn get_diff(&self) -> Option<&GitDiff> {
+        self.diff.as_ref()
+    }
+
+    pub fn get_last_commit(&self) -> Option<&CommitInfo> {
+        self.last_commit.as_ref()
+    }
+
+    pub fn get_churn(&self) -> Option<Churn> {
+        self.churn
+    }
+
+    pub fn is_ignored(&self) -> bool {
+        self.ignored
+    }
+
+    pub fn get_symlink(&self) -> Option<&Symlink> {
+        self.symlink.as_ref()
+    }
+
+    pub fn is_mount_point(&self) -> bool {
+        self.mount_point
+    }
+
+    pub fn get_repository(&self) -> Option<&RepoBoundary> {
+        self.repository.as_ref()
+    }
+
+    pub fn get_verbose_info(&self) -> Option<&MetaDataInfo> {
+        self.vervose_info.as_ref()
+    }
 
-        let is_show_hidden = matches.get_flag("all");
+    pub fn get_timestamps(&self) -> &Timestamps {
+        &self.timestamps
+    }
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        Self::new(".")
+    }
+}
+
+impl TreeOptions {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
         Self {
-            path: path,
-            extensions: exts,
-            ignores: ignores,
-            size: size,
-            max_depth: max_depth,
-            output_format: output_format,
-            git_intergration: git_intergration,
-            verbose: is_verbose,
-            sort: sort,
-            mode: mode,
-            is_show_hidden: is_show_hidden,
+            path: path.into(),
+            extensions: Vec::new(),
+            exclude_extensions: Vec::new(),
+            prune: false,
+            size: None,
+            ignores: Vec::new(),
+            includes: Vec::new(),
+            regex: false,
+            max_depth: None,
+            follow: false,
+            one_file_system: false,
+            git_integration: false,
+            git_changed: None,
+            recurse_submodules: false,
+            show_ignored: false,
+            rev: None,
+            diff: None,
+            git_log: false,
+            git_churn: false,
+            since: None,
+            verbose: false,
+            sort: SortSpec::default(),
+            mode: Mode::Sync,
+            jobs: None,
+            is_show_hidden: false,
+            size_filtered: false,
+            gitignore: None,
         }
     }
 
-    fn ext_filter(&self, entry: &DirEntry) -> bool {
-        !entry
-            .path()
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| self.extensions.contains(&ext.to_string()))
-            .unwrap_or(false)
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.path = path.into();
+        self
     }
 
-    fn ext_filter_tokio(&self, entry: &tokio::fs::DirEntry) -> bool {
-        !entry
-            .path()
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| self.extensions.contains(&ext.to_string()))
-            .unwrap_or(false)
+    /// ## Summary
+    /// 表示するファイルの拡張子。大文字小文字は区別せず、`tar.gz`も指定できる
+    /// ディレクトリには適用されず、一致するファイルを含むディレクトリだけが表示される
+    pub fn extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions = extensions.into_iter().map(Into::into).collect();
+        self
     }
 
-    fn ignore_filename_filter(&self, entry: &DirEntry) -> bool {
-        let filename = entry.file_name().to_string_lossy().to_string();
-        !self.ignores.contains(&filename)
+    /// ## Summary
+    /// 表示しないファイルの拡張子
+    pub fn exclude_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.exclude_extensions = extensions.into_iter().map(Into::into).collect();
+        self
     }
 
-    fn ignore_filename_filter_tokio(&self, entry: &tokio::fs::DirEntry) -> bool {
-        let filename = entry.file_name().to_string_lossy().to_string();
-        !self.ignores.contains(&filename)
+    /// ## Summary
+    /// フィルターの結果、表示するものがなくなったディレクトリを表示しない
+    /// `extensions`を指定した場合は常に表示しない
+    pub fn prune(mut self, prune: bool) -> Self {
+        self.prune = prune;
+        self
     }
-}
 
-pub fn get_git_statuses(repo_path: &Path) -> HashMap<PathBuf, Status> {
-    let repo = Repository::discover(repo_path).expect("Not a git repository");
-    let mut statuses = HashMap::new();
-    let mut status_options = git2::StatusOptions::new();
-    let git_statuses = repo
-        .statuses(Some(&mut status_options))
-        .expect("Failed to get git statuses");
-
-    for entry in git_statuses.iter() {
-        if let Some(path) = entry.path() {
-            statuses.insert(repo_path.join(path), entry.status());
+    pub fn ignores<I, S>(mut self, ignores: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.ignores = ignores.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// ## Summary
+    /// 表示するファイルのパターン。ディレクトリには適用されない
+    /// パターンはファイル名とルートからの相対パスの両方に対して判定される
+    pub fn includes<I, S>(mut self, includes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.includes = includes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// ## Summary
+    /// `ignores`と`includes`をglobではなく正規表現として扱う
+    pub fn regex(mut self, regex: bool) -> Self {
+        self.regex = regex;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// ## Summary
+    /// ディレクトリへのシンボリックリンクを辿って中身も表示する
+    /// 走査中のディレクトリの祖先に戻るリンクは辿らない
+    pub fn follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    /// ## Summary
+    /// ルートと別のファイルシステムにあるディレクトリの中を走査しない
+    /// マウントポイントは中身のないディレクトリとして表示する
+    ///
+    /// ## Note
+    /// デバイスIDが取得できないWindowsでは効果がない
+    pub fn one_file_system(mut self, one_file_system: bool) -> Self {
+        self.one_file_system = one_file_system;
+        self
+    }
+
+    pub fn size(mut self, size: SizeFormat) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    pub fn sort(mut self, sort: SortKey) -> Self {
+        self.sort.keys = vec![sort];
+        self
+    }
+
+    /// ## Summary
+    /// 複数のソートキー。先頭のキーから順に比較する
+    pub fn sort_keys<I: IntoIterator<Item = SortKey>>(mut self, keys: I) -> Self {
+        self.sort.keys = keys.into_iter().collect();
+        self
+    }
+
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.sort.reverse = reverse;
+        self
+    }
+
+    pub fn dirs_first(mut self, dirs_first: bool) -> Self {
+        self.sort.dirs_first = dirs_first;
+        self
+    }
+
+    /// ## Summary
+    /// 名前の数字部分を数値として比較する(`file2` < `file10`)
+    pub fn natural_sort(mut self, natural: bool) -> Self {
+        self.sort.natural = natural;
+        self
+    }
+
+    /// ## Summary
+    /// 名前と拡張子を大文字小文字を区別せずに比較する
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.sort.ignore_case = ignore_case;
+        self
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// ## Summary
+    /// 並列・非同期モードで同時に処理する数
+    /// 指定しない場合は利用可能なCPU数
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    pub fn show_hidden(mut self, show_hidden: bool) -> Self {
+        self.is_show_hidden = show_hidden;
+        self
+    }
+
+    /// ## Summary
+    /// `-a`・`--ext`・`--ignore`で表示されないファイルもディレクトリのサイズに含める
+    pub fn size_filtered(mut self, size_filtered: bool) -> Self {
+        self.size_filtered = size_filtered;
+        self
+    }
+
+    /// ## Summary
+    /// `.gitignore`・`.ignore`・`.git/info/exclude`・`core.excludesFile`を適用するかどうか
+    /// 指定しない場合はgitリポジトリの中でだけ適用する
+    pub fn gitignore(mut self, gitignore: bool) -> Self {
+        self.gitignore = Some(gitignore);
+        self
+    }
+
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn git_integration(mut self, git_integration: bool) -> Self {
+        self.git_integration = git_integration;
+        self
+    }
+
+    /// ## Summary
+    /// git statusのあるファイルとその祖先のディレクトリだけを表示する
+    /// `classes`が空の場合はすべての種類の変更を表示する
+    /// git連携も有効になる
+    pub fn git_changed(mut self, classes: impl IntoIterator<Item = StatusClass>) -> Self {
+        self.git_changed = Some(classes.into_iter().collect());
+        self
+    }
+
+    /// ## Summary
+    /// gitで無視されているファイル・ディレクトリを隠さずに`!!`を付けて表示する
+    /// ignoreファイルによる除外は行わない
+    pub fn show_ignored(mut self, show_ignored: bool) -> Self {
+        self.show_ignored = show_ignored;
+        self
+    }
+
+    /// ## Summary
+    /// サブモジュールの中のファイルのgit statusも表示する
+    /// git連携も有効になる
+    pub fn recurse_submodules(mut self, recurse_submodules: bool) -> Self {
+        self.recurse_submodules = recurse_submodules;
+        self
+    }
+
+    /// ## Summary
+    /// ワークツリーの代わりにコミット・ブランチ・タグのツリーを表示する
+    /// gitignoreとgit statusは適用しない
+    pub fn rev(mut self, rev: impl Into<String>) -> Self {
+        self.rev = Some(rev.into());
+        self
+    }
+
+    /// ## Summary
+    /// 2つのリビジョンのツリーを重ね合わせ、`old`から`new`への変更を表示する
+    /// gitignoreとgit statusは適用しない
+    pub fn diff(mut self, old: impl Into<String>, new: impl Into<String>) -> Self {
+        self.diff = Some((old.into(), new.into()));
+        self
+    }
+
+    /// ## Summary
+    /// 各ファイル・ディレクトリを最後に変更したコミットを表示する
+    /// `rev`・`diff`が指定されていればその時点(比較先)から履歴を辿る
+    pub fn git_log(mut self, git_log: bool) -> Self {
+        self.git_log = git_log;
+        self
+    }
+
+    /// ## Summary
+    /// gitの履歴での変更回数と行数を表示し、変更の多さで色を付ける
+    pub fn git_churn(mut self, git_churn: bool) -> Self {
+        self.git_churn = git_churn;
+        self
+    }
+
+    /// ## Summary
+    /// `--git-churn`で数えるコミットの開始日時(UNIX時間)
+    pub fn since(mut self, since: i64) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// ## Summary
+    /// ソートキーに必要な情報を取得するようにフラグを補正したオプションを返す
+    /// サイズでソートする場合はサイズを取得する
+    /// タイムスタンプは詳細情報を有効にせず、走査時に必要な種類だけ取得する
+    fn resolved(&self) -> Self {
+        let mut options = self.clone();
+        if options.sort.contains(&SortKey::Size) && options.size.is_none() {
+            options.size = Some(SizeFormat::Bytes);
         }
+        if options.git_changed.is_some() || options.recurse_submodules {
+            options.git_integration = true;
+        }
+        if options.show_ignored {
+            options.gitignore = Some(false);
+        }
+        if options.sort.contains(&SortKey::Churn) {
+            options.git_churn = true;
+        }
+        // コミットに記録されたファイルはignoreの対象ではなく、ワークツリーの状態とも関係がない
+        if options.rev.is_some() || options.diff.is_some() {
+            options.gitignore = Some(false);
+            options.git_integration = false;
+            options.git_changed = None;
+            options.show_ignored = false;
+        }
+        options
+    }
+}
+
+/// ## Summary
+/// オプションに従ってツリーを構築する
+/// git連携・実行モード・ソートはすべてここで解決される
+///
+/// ## Returns
+/// ルートのTreeNode or ルートが除外された/読めなかった場合のError
+pub fn build_tree(options: &TreeOptions) -> Result<TreeNode> {
+    let options = options.resolved();
+    let root = options.path.as_path();
+    let mut node = open_walker(&options, None)?
+        .walk(root)
+        .ok_or_else(|| anyhow!("cannot build tree for {}", root.display()))?;
+
+    if options.sort.is_active() {
+        node.sort(&options.sort);
     }
 
-    statuses
+    Ok(node)
+}
+
+/// ## Summary
+/// ツリーをメモリに構築せず、走査しながら1エントリ1行のJSON(NDJSON)で書き出す
+/// ディレクトリは中身の後に出力され、`--sort`は適用されない
+/// 並列・非同期モードでは走査が終わった順に出力される
+///
+/// ## Parameters
+/// - `writer`: 出力先
+///
+/// ## Returns
+/// () or 走査の開始・書き込みに失敗した場合のError
+pub fn stream_tree<W: Write + Send + 'static>(options: &TreeOptions, writer: W) -> Result<()> {
+    let options = options.resolved();
+    if options.diff.is_some() {
+        // ディレクトリの変更は子の変更から集計するので、子を書き出した後では求められない
+        return Err(anyhow!("--diff cannot be streamed as NDJSON"));
+    }
+    let sink = NdjsonSink::new(writer);
+    open_walker(&options, Some(sink.clone()))?.walk(&options.path);
+    sink.finish()?;
+    Ok(())
+}
+
+/// ## Summary
+/// オプションに合わせてgit statusを読み込み、Walkerを作る
+fn open_walker(options: &TreeOptions, sink: Option<NdjsonSink>) -> Result<Box<dyn Walker>> {
+    let root = options.path.as_path();
+    let mut git_statuses = if options.git_integration || options.show_ignored {
+        get_git_statuses(root, options.show_ignored)
+    } else {
+        HashMap::new()
+    };
+    // git連携なしで無視されたファイルだけを表示する場合は他の状態を表示しない
+    if !options.git_integration {
+        git_statuses.retain(|_, status| status.is_ignored());
+    }
+    if options.git_integration && options.recurse_submodules {
+        // サブモジュールのルートは含まれないので、親のリポジトリの状態を上書きしない
+        git_statuses.extend(submodule_statuses(root));
+    }
+
+    let jobs = options.jobs.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+    let mut pipeline = Pipeline::new(options.clone(), git_statuses)?;
+    if let Some(sink) = sink {
+        pipeline = pipeline.with_sink(sink);
+    }
+    let walker: Box<dyn Walker> = match (&options.rev, &options.diff) {
+        (_, Some((old, new))) => Box::new(DiffWalker::open(pipeline, old, new)?),
+        (Some(rev), None) => Box::new(RevWalker::open(pipeline, rev)?),
+        (None, None) => walker_for(&options.mode, pipeline, jobs),
+    };
+    Ok(walker)
 }
 
 pub fn print_tree(node: &TreeNode, prefix: &str, is_last: bool) {
+    print_node(node, prefix, is_last, &HeatScale::new(node));
+}
+
+fn print_node(node: &TreeNode, prefix: &str, is_last: bool, heat: &HeatScale) {
     // prefixが空なの時に対応しているのは
     // 初回実行時のみ空文字でありbranchを入れると崩れるため空文字にしている
     let connector = if prefix.is_empty() {
         STR_EMPTY
+    } else if is_last {
+        TREE_LAST_BRANCH
     } else {
-        if is_last {
-            TREE_LAST_BRANCH
-        } else {
-            TREE_BRANCH
-        }
+        TREE_BRANCH
     };
 
-    let status = node.git_status.as_deref().unwrap_or("");
-    let colored_name = if status.contains("WT_MODIFIED") {
-        node.name.yellow()
-    } else if status.contains("WT_NEW") {
-        node.name.green()
-    } else if node.children.is_some() {
-        node.name.blue()
-    } else {
-        node.name.white()
+    let (marker, colored_name) = match (&node.diff, node.git_status) {
+        (Some(diff), _) => {
+            let color = change_color(diff.status.marker());
+            (
+                format!("[{}] ", diff.status.marker()).color(color),
+                node.name.color(color),
+            )
+        }
+        (None, Some(status)) => (
+            format!("[{}] ", status).color(status_color(status)),
+            node.name.color(status_color(status)),
+        ),
+        (None, None) if node.symlink.as_ref().is_some_and(|symlink| symlink.broken) => {
+            (STR_EMPTY.normal(), node.name.red().bold())
+        }
+        // ルートは常に最も変更が多いので色を付けない
+        (None, None) => match heat.color(node).filter(|_| !prefix.is_empty()) {
+            Some(color) => (STR_EMPTY.normal(), node.name.color(color)),
+            None if node.symlink.is_some() => (STR_EMPTY.normal(), node.name.cyan()),
+            None if node.children.is_some() || node.mount_point => {
+                (STR_EMPTY.normal(), node.name.blue())
+            }
+            None => (STR_EMPTY.normal(), node.name.white()),
+        },
+    };
+    let colored_name = match node.git_status {
+        Some(status) if status.is_conflicted() => colored_name.bold(),
+        Some(status) if status.is_ignored() => colored_name.dimmed(),
+        _ => colored_name,
     };
 
     let info = node.size_and_verbose_print_format();
+    let last_commit = node
+        .last_commit
+        .as_ref()
+        .map(|commit| format!("  {}", commit.summary_line()).dimmed())
+        .unwrap_or_default();
+    let churn = node
+        .churn
+        .map(|churn| {
+            format!(
+                " {} commits +{} -{}",
+                churn.commits, churn.additions, churn.deletions
+            )
+            .color(heat.color(node).unwrap_or(Color::White))
+        })
+        .unwrap_or_default();
 
-    println!("{}{}{}{}", prefix, connector, colored_name, info);
+    println!(
+        "{}{}{}{}{}{}{}{}{}",
+        prefix,
+        connector,
+        marker,
+        colored_name,
+        node.suffix(),
+        diff_print_format(node.diff.as_ref()),
+        churn,
+        info,
+        last_commit
+    );
 
     if let Some(children) = &node.children {
         let new_prefix = format!(
@@ -304,288 +804,129 @@ pub fn print_tree(node: &TreeNode, prefix: &str, is_last: bool) {
         );
         let len = children.len();
         for (i, child) in children.iter().enumerate() {
-            print_tree(child, &new_prefix, i == len - 1);
+            print_node(child, &new_prefix, i == len - 1, heat);
         }
     }
 }
-pub fn build_tree(
-    path: &Path,
-    depth: u32,
-    tree: &Tree,
-    git_statuses: &HashMap<PathBuf, Status>,
-) -> Option<TreeNode> {
-    if let Some(max_depth) = tree.max_depth {
-        if depth > max_depth {
-            return None;
-        }
-    }
-    let name = utils::files::get_filename(path);
 
-    // -aが指定されてないかつ隠しフォルダーならskip
-    if !tree.is_show_hidden && name.starts_with(".") {
-        return None;
-    }
-
-    if tree.ignores.contains(&name) {
-        return None;
-    }
-
-    let git_status = git_statuses.get(path).map(|status| format!("{:?}", status));
+/// ## Summary
+/// `--git-churn`で変更の多さを色で表すための基準
+/// ファイルとディレクトリはそれぞれの最大の変更回数に対する割合で色を決める
+struct HeatScale {
+    files: u64,
+    dirs: u64,
+}
 
-    let vervose_info = if tree.verbose {
-        utils::files::get_metadata(&path)
-            .map_err(|e| eprintln!("ERROR: {}", e))
-            .ok()
-    } else {
-        None
-    };
+impl HeatScale {
+    const COLORS: [Color; 5] = [
+        Color::Blue,
+        Color::Cyan,
+        Color::Green,
+        Color::Yellow,
+        Color::Red,
+    ];
 
-    if path.is_dir() {
-        let children: Vec<TreeNode> = fs::read_dir(&path)
-            .ok()?
-            .filter_map(Result::ok)
-            .filter(|entry| tree.ext_filter(entry))
-            .filter(|entry| tree.ignore_filename_filter(entry))
-            .filter_map(|entry| build_tree(&entry.path(), depth + 1, tree, git_statuses))
-            .collect();
-
-        let size = if let Some(size) = &tree.size {
-            match size {
-                SizeFormat::Bytes => utils::files::get_filesize(&path)
-                    .map_err(|e| eprintln!("ERROR: {}", e))
-                    .ok()
-                    .map(|s| size::Unit::Byte(s)),
-                SizeFormat::HumanReadable => utils::files::get_human_readable_filesize(&path)
-                    .map_err(|e| eprintln!("{}", e))
-                    .ok(),
-            }
-        } else {
-            None
-        };
+    /// ## Summary
+    /// ルートを除くノードから最大の変更回数を求める
+    fn new(root: &TreeNode) -> Self {
+        let mut scale = Self { files: 0, dirs: 0 };
+        scale.collect(root.children.iter().flatten());
+        scale
+    }
 
-        Some(TreeNode {
-            name,
-            git_status: git_status,
-            children: if children.is_empty() {
-                None
+    fn collect<'a>(&mut self, nodes: impl Iterator<Item = &'a TreeNode>) {
+        for node in nodes {
+            let commits = node.churn.map_or(0, |churn| churn.commits);
+            if node.is_dir {
+                self.dirs = self.dirs.max(commits);
             } else {
-                Some(children)
-            },
-            size: size,
-            vervose_info: vervose_info,
-        })
-    } else {
-        let size = if let Some(size_format) = &tree.size {
-            match size_format {
-                SizeFormat::Bytes => {
-                    let metadata = path.metadata().map_err(|e| eprintln!("ERROR: {}", e)).ok();
-                    metadata.map(|m| size::Unit::Byte(m.len()))
-                }
-                SizeFormat::HumanReadable => utils::files::get_human_readable_filesize(path)
-                    .map_err(|e| eprintln!("ERROR:{}", e))
-                    .ok(),
+                self.files = self.files.max(commits);
             }
-        } else {
-            None
-        };
-        Some(TreeNode {
-            name,
-            git_status: git_status,
-            children: None,
-            size: size,
-            vervose_info: vervose_info,
-        })
+            self.collect(node.children.iter().flatten());
+        }
     }
-}
 
-pub fn build_tree_parallel(
-    path: &Path,
-    depth: u32,
-    tree: &Tree,
-    git_statuses: &HashMap<PathBuf, Status>,
-) -> Option<TreeNode> {
-    if let Some(max_depth) = tree.max_depth {
-        if depth > max_depth {
+    /// ## Summary
+    /// 変更が少ないものから青・シアン・緑・黄・赤の順で色を返す
+    fn color(&self, node: &TreeNode) -> Option<Color> {
+        let churn = node.churn?;
+        let max = if node.is_dir { self.dirs } else { self.files };
+        if max == 0 {
             return None;
         }
-    }
-    let name = utils::files::get_filename(path);
-    // -aが指定されてないかつ隠しフォルダーならskip
-    if !tree.is_show_hidden && name.starts_with(".") {
-        return None;
-    }
-    if tree.ignores.contains(&name) {
-        return None;
-    }
-
-    let git_status = git_statuses.get(path).map(|status| format!("{:?}", status));
-
-    let vervose_info = if tree.verbose {
-        utils::files::get_metadata(&path)
-            .map_err(|e| eprintln!("ERROR: {}", e))
-            .ok()
-    } else {
-        None
-    };
-
-    if path.is_dir() {
-        let children: Vec<TreeNode> = fs::read_dir(&path)
-            .ok()?
-            .par_bridge()
-            .filter_map(Result::ok)
-            .filter(|entry| tree.ext_filter(entry))
-            .filter(|entry| tree.ignore_filename_filter(entry))
-            .filter_map(|entry| build_tree_parallel(&entry.path(), depth + 1, tree, git_statuses))
-            .collect();
-
-        let size = if let Some(size) = &tree.size {
-            match size {
-                SizeFormat::Bytes => utils::files::get_filesize(&path)
-                    .map_err(|e| eprintln!("ERROR: {}", e))
-                    .ok()
-                    .map(|s| size::Unit::Byte(s)),
-                SizeFormat::HumanReadable => utils::files::get_human_readable_filesize(&path)
-                    .map_err(|e| eprintln!("{}", e))
-                    .ok(),
-            }
-        } else {
-            None
-        };
-
-        Some(TreeNode {
-            name,
-            git_status: git_status,
-            children: if children.is_empty() {
-                None
-            } else {
-                Some(children)
-            },
-            size: size,
-            vervose_info: vervose_info,
-        })
-    } else {
-        let size = if let Some(size_format) = &tree.size {
-            match size_format {
-                SizeFormat::Bytes => {
-                    let metadata = path.metadata().map_err(|e| eprintln!("ERROR: {}", e)).ok();
-                    metadata.map(|m| size::Unit::Byte(m.len()))
-                }
-                SizeFormat::HumanReadable => utils::files::get_human_readable_filesize(path)
-                    .map_err(|e| eprintln!("ERROR:{}", e))
-                    .ok(),
-            }
-        } else {
-            None
-        };
-        Some(TreeNode {
-            name,
-            git_status: git_status,
-            children: None,
-            size: size,
-            vervose_info: vervose_info,
-        })
+        let last = Self::COLORS.len() - 1;
+        let level = (churn.commits * last as u64).div_ceil(max) as usize;
+        Some(Self::COLORS[level.min(last)])
     }
 }
 
-#[async_recursion]
-pub async fn build_tree_async(
-    path: &Path,
-    depth: u32,
-    tree: &Tree,
-    git_statuses: &HashMap<PathBuf, Status>,
-) -> Option<TreeNode> {
-    if let Some(max_depth) = tree.max_depth {
-        if depth > max_depth {
-            return None;
-        }
+/// ## Summary
+/// git statusの種類ごとの表示色
+/// ワークツリーの変更を優先し、ステージ済みのみの変更はインデックス側の種類で色を決める
+fn status_color(status: GitStatus) -> Color {
+    if status.is_conflicted() {
+        return Color::BrightRed;
     }
-    let name = utils::files::get_filename(path);
-    // -aが指定されてないかつ隠しフォルダーならskip
-    if !tree.is_show_hidden && name.starts_with(".") {
-        return None;
+    if status.is_ignored() {
+        return Color::BrightBlack;
     }
-    if tree.ignores.contains(&name) {
-        return None;
+    if status.is_untracked() {
+        return Color::Green;
     }
-
-    let git_status = git_statuses.get(path).map(|status| format!("{:?}", status));
-
-    let vervose_info = if tree.verbose {
-        utils::files::get_metadata_async(&path)
-            .await
-            .map_err(|e| eprintln!("ERROR: {}", e))
-            .ok()
-    } else {
-        None
+    let kind = match status.worktree {
+        ' ' | '?' => status.index,
+        worktree => worktree,
     };
+    change_color(kind)
+}
 
-    if path.is_dir() {
-        let mut entries = tokio::fs::read_dir(path).await.ok()?;
-
-        let mut children = vec![];
-        while let Some(entry) = entries.next_entry().await.ok()? {
-            if !tree.ext_filter_tokio(&entry) {
-                continue;
-            }
-            if !tree.ignore_filename_filter_tokio(&entry) {
-                continue;
-            }
-            let child = build_tree_async(&entry.path(), depth + 1, tree, git_statuses).await?;
-            children.push(child);
-        }
-        let size = if let Some(size) = &tree.size {
-            match size {
-                SizeFormat::Bytes => utils::files::get_filesize_async_safe(&path)
-                    .await
-                    .map_err(|e| eprintln!("ERROR: {}", e))
-                    .ok()
-                    .map(|s| size::Unit::Byte(s)),
-                SizeFormat::HumanReadable => utils::files::get_human_readable_filesize_async(&path)
-                    .await
-                    .map_err(|e| eprintln!("{}", e))
-                    .ok(),
-            }
-        } else {
-            None
-        };
+/// ## Summary
+/// `A`・`M`・`D`・`R`・`T`の変更の種類ごとの表示色
+/// git statusと`--diff`で共通
+fn change_color(kind: char) -> Color {
+    match kind {
+        'A' => Color::Green,
+        'M' => Color::Yellow,
+        'D' => Color::Red,
+        'R' => Color::Cyan,
+        'T' => Color::Magenta,
+        _ => Color::White,
+    }
+}
 
-        Some(TreeNode {
-            name,
-            git_status: git_status,
-            children: if children.is_empty() {
-                None
-            } else {
-                Some(children)
-            },
-            size: size,
-            vervose_info: vervose_info,
-        })
-    } else {
-        let size = if let Some(size_format) = &tree.size {
-            match size_format {
-                SizeFormat::Bytes => {
-                    let metadata = path.metadata().map_err(|e| eprintln!("ERROR: {}", e)).ok();
-                    metadata.map(|m| size::Unit::Byte(m.len()))
-                }
-                SizeFormat::HumanReadable => utils::files::get_human_readable_filesize(path)
-                    .map_err(|e| eprintln!("ERROR:{}", e))
-                    .ok(),
-            }
-        } else {
-            None
-        };
-        Some(TreeNode {
-            name,
-            git_status: git_status,
-            children: None,
-            size: size,
-            vervose_info: vervose_info,
-        })
+/// ## Summary
+/// `--diff`のリネーム前のパスと変更行数
+fn diff_print_format(diff: Option<&GitDiff>) -> String {
+    let Some(diff) = diff else {
+        return String::new();
+    };
+    let mut result = String::new();
+    if let Some(old_path) = &diff.old_path {
+        result.push_str(&format!(" <- {}", old_path));
     }
+    if diff.additions > 0 || diff.deletions > 0 {
+        result.push_str(&format!(
+            " {} {}",
+            format!("+{}", diff.additions).green(),
+            format!("-{}", diff.deletions).red()
+        ));
+    }
+    result
 }
 
 pub fn tree_to_markdown(node: &TreeNode, depth: usize) -> String {
-    let mut markdown = format!("{}- {}\n", "  ".repeat(depth), node.name);
+    let last_commit = node
+        .last_commit
+        .as_ref()
+        .map(|commit| format!(" — {}", commit.summary_line()))
+        .unwrap_or_default();
+    let mut markdown = format!(
+        "{}- {}{}{}\n",
+        "  ".repeat(depth),
+        node.name,
+        node.suffix(),
+        last_commit
+    );
     if let Some(children) = &node.children {
         for child in children {
             markdown.push_str(&tree_to_markdown(child, depth + 1));
@@ -610,3 +951,159 @@ pub fn collect_stats(node: &TreeNode, stats: &mut HashMap<String, (u64, f64)>) {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn file(name: &str, size: u64) -> TreeNode {
+        TreeNode::new(name.into(), None, None, Some(Unit::Byte(size)), None)
+    }
+
+    fn dir(name: &str, children: Vec<TreeNode>) -> TreeNode {
+        TreeNode::new(name.into(), None, Some(children), None, None)
+    }
+
+    fn names(node: &TreeNode) -> Vec<&str> {
+        node.children
+            .iter()
+            .flatten()
+            .map(|child| child.get_name())
+            .collect()
+    }
+
+    fn sorted(mut node: TreeNode, spec: SortSpec) -> TreeNode {
+        node.sort(&spec);
+        node
+    }
+
+    fn root() -> TreeNode {
+        dir(
+            "root",
+            vec![
+                file("b.rs", 30),
+                file("file10.txt", 10),
+                dir("src", vec![file("z.rs", 1), file("a.rs", 2)]),
+                file("A.md", 20),
+                file("file2.txt", 10),
+                file("Makefile", 5),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_sort_by_churn() {
+        let with_churn = |mut node: TreeNode, commits: u64, lines: u64| {
+            node.churn = Some(Churn {
+                commits,
+                additions: lines,
+                deletions: 0,
+            });
+            node
+        };
+        let node = dir(
+            "root",
+            vec![
+                with_churn(file("hot.rs", 1), 9, 100),
+                file("untracked.rs", 1),
+                with_churn(file("warm.rs", 1), 3, 50),
+                with_churn(file("busy.rs", 1), 3, 80),
+            ],
+        );
+        let spec = SortSpec {
+            keys: vec![SortKey::Churn],
+            reverse: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            names(&sorted(node, spec)),
+            ["hot.rs", "busy.rs", "warm.rs", "untracked.rs"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_ext_then_name() {
+        let spec = SortSpec {
+            keys: vec![SortKey::Ext, SortKey::Name],
+            ..Default::default()
+        };
+        let node = sorted(root(), spec);
+
+        assert_eq!(
+            names(&node),
+            vec!["Makefile", "src", "A.md", "b.rs", "file10.txt", "file2.txt"]
+        );
+        assert_eq!(
+            names(&node.children.as_ref().unwrap()[1]),
+            vec!["a.rs", "z.rs"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_size_then_name_reversed() {
+        let spec = SortSpec {
+            keys: vec![SortKey::Size, SortKey::Name],
+            reverse: true,
+            ..Default::default()
+        };
+        let node = sorted(root(), spec);
+
+        assert_eq!(
+            names(&node),
+            vec!["src", "b.rs", "A.md", "file2.txt", "file10.txt", "Makefile"]
+        );
+    }
+
+    #[test]
+    fn test_sort_dirs_first_is_not_reversed() {
+        let spec = SortSpec {
+            reverse: true,
+            dirs_first: true,
+            ..Default::default()
+        };
+        let node = sorted(root(), spec);
+
+        assert_eq!(
+            names(&node),
+            vec!["src", "file2.txt", "file10.txt", "b.rs", "Makefile", "A.md"]
+        );
+    }
+
+    #[test]
+    fn test_sort_natural_ignore_case() {
+        let spec = SortSpec {
+            natural: true,
+            ignore_case: true,
+            ..Default::default()
+        };
+        let node = sorted(root(), spec);
+
+        assert_eq!(
+            names(&node),
+            vec!["A.md", "b.rs", "file2.txt", "file10.txt", "Makefile", "src"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_mtime_without_verbose() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        for (name, age) in [("old.txt", 300), ("new.txt", 0), ("mid.txt", 100)] {
+            let file = std::fs::File::create(dir.path().join(name)).unwrap();
+            file.set_modified(now - Duration::from_secs(age)).unwrap();
+        }
+
+        let options = TreeOptions::new(dir.path()).sort(SortKey::Mtime);
+        let node = build_tree(&options).unwrap();
+
+        assert_eq!(names(&node), vec!["old.txt", "mid.txt", "new.txt"]);
+        assert!(node
+            .children
+            .iter()
+            .flatten()
+            .all(|child| child.get_verbose_info().is_none()));
+    }
+}
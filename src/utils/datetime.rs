@@ -2,7 +2,7 @@ use std::{ops::Deref, time::SystemTime};
 
 use chrono::{
     format::{DelayedFormat, StrftimeItems},
-    DateTime, TimeZone, Utc,
+    DateTime, NaiveDate, NaiveTime, TimeZone, Utc,
 };
 
 pub fn yyyy_mm_dd_format(time: SystemTime) -> String {
@@ -10,9 +10,79 @@ pub fn yyyy_mm_dd_format(time: SystemTime) -> String {
     datetime.format("%Y-%m-%d").to_string()
 }
 
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd)]
+/// ## Summary
+/// `now`から見た経過時間を`3 days ago`のような形式で返す
+///
+/// ## Parameters
+/// - `time`・`now`: UNIX時間(秒)
+pub fn relative_format(time: i64, now: i64) -> String {
+    const UNITS: [(i64, &str); 6] = [
+        (60 * 60 * 24 * 365, "year"),
+        (60 * 60 * 24 * 30, "month"),
+        (60 * 60 * 24 * 7, "week"),
+        (60 * 60 * 24, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+
+    let elapsed = (now - time).max(0);
+    for (seconds, unit) in UNITS {
+        let count = elapsed / seconds;
+        if count > 0 {
+            let plural = if count == 1 { "" } else { "s" };
+            return format!("{} {}{} ago", count, unit, plural);
+        }
+    }
+    "just now".to_string()
+}
+
+/// ## Summary
+/// `--since`の日時をUNIX時間(秒)にする
+/// `2024-01-31`・RFC 3339・`3 months`(`3 months ago`)の形式を受け付ける
+///
+/// ## Parameters
+/// - `now`: 相対的な指定の基準にするUNIX時間
+pub fn parse_since(value: &str, now: i64) -> Result<i64, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN).and_utc().timestamp());
+    }
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.timestamp());
+    }
+
+    let invalid = || format!("Invalid date:{}", value);
+    let relative = value.trim().trim_end_matches("ago").trim_end();
+    let (count, unit) = relative.split_once(' ').ok_or_else(invalid)?;
+    let count: i64 = count.parse().map_err(|_| invalid())?;
+    let seconds = match unit.trim().trim_end_matches('s') {
+        "minute" => 60,
+        "hour" => 60 * 60,
+        "day" => 60 * 60 * 24,
+        "week" => 60 * 60 * 24 * 7,
+        "month" => 60 * 60 * 24 * 30,
+        "year" => 60 * 60 * 24 * 365,
+        _ => return Err(invalid()),
+    };
+    Ok(now - count * seconds)
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct DateTimeWrap(DateTime<Utc>);
 
+/// ## Summary
+/// ファイルのタイムスタンプの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeKind {
+    /// 最終更新日時(mtime)
+    Modified,
+    /// 最終アクセス日時(atime)
+    Accessed,
+    /// inodeの変更日時(ctime)。Unixのみ
+    Changed,
+    /// 作成日時(btime)。Linuxではstatxが使える場合のみ
+    Born,
+}
+
 impl From<i64> for DateTimeWrap {
     fn from(value: i64) -> Self {
         Self(Utc.timestamp_opt(value, 0).unwrap())
@@ -26,6 +96,12 @@ impl From<SystemTime> for DateTimeWrap {
 }
 
 impl DateTimeWrap {
+    /// ## Summary
+    /// UNIX時間の秒とナノ秒から作る
+    pub fn from_timestamp(secs: i64, nsecs: u32) -> Self {
+        Self(Utc.timestamp_opt(secs, nsecs).unwrap())
+    }
+
     pub fn yyyy_mm_dd_format(&self) -> DelayedFormat<StrftimeItems<'_>> {
         self.format("%Y-%m-%d")
     }
@@ -38,3 +114,33 @@ impl Deref for DateTimeWrap {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        let now = 1_700_000_000;
+        assert_eq!(parse_since("2024-01-31", now), Ok(1_706_659_200));
+        assert_eq!(
+            parse_since("2024-01-31T09:00:00+09:00", now),
+            Ok(1_706_659_200)
+        );
+        assert_eq!(parse_since("3 days", now), Ok(now - 3 * 24 * 60 * 60));
+        assert_eq!(parse_since("1 week ago", now), Ok(now - 7 * 24 * 60 * 60));
+        assert!(parse_since("yesterday", now).is_err());
+        assert!(parse_since("3 fortnights", now).is_err());
+    }
+
+    #[test]
+    fn test_relative_format() {
+        let now = 1_700_000_000;
+        assert_eq!(relative_format(now - 10, now), "just now");
+        assert_eq!(relative_format(now - 60, now), "1 minute ago");
+        assert_eq!(relative_format(now - 60 * 60 * 5, now), "5 hours ago");
+        assert_eq!(relative_format(now - 60 * 60 * 24 * 3, now), "3 days ago");
+        assert_eq!(relative_format(now - 60 * 60 * 24 * 400, now), "1 year ago");
+        assert_eq!(relative_format(now + 100, now), "just now");
+    }
+}
@@ -1,7 +1,4 @@
-use std::{
-    fmt, fs,
-    path::{Path, PathBuf},
-};
+use std::{fmt, fs, io, path::Path};
 
 use anyhow::Result;
 use async_recursion::async_recursion;
@@ -14,13 +11,13 @@ use std::os::windows::fs::MetadataExt;
 use std::os::unix::fs::MetadataExt;
 
 use super::{
-    datetime::DateTimeWrap,
-    size::{Unit, ONE_KELE_BYTE_F32},
+    datetime::{DateTimeWrap, TimeKind},
+    size::Unit,
 };
 
 const CURRENT_DIR: &str = ".";
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct MetaDataInfo {
     pub size: u64,
 
@@ -30,6 +27,10 @@ pub struct MetaDataInfo {
     #[cfg(unix)]
     pub group: u32,
 
+    /// パーミッションを含むファイルモード(`st_mode`)
+    #[cfg(unix)]
+    pub mode: u32,
+
     pub created: DateTimeWrap,
 
     pub modified: DateTimeWrap,
@@ -83,6 +84,20 @@ pub fn get_filename<P: AsRef<Path>>(path: P) -> String {
 ///
 ///```
 pub fn get_filesize<P: AsRef<Path>>(directory: P) -> Result<u64> {
+    get_filesize_on_device(directory, None)
+}
+
+/// ## Summary
+/// ディレクトリサイズを取得(再帰的)
+/// `device`を指定した場合は別のファイルシステムにあるディレクトリの中は数えない
+///
+/// ## Parameters
+/// - `directory`: ディレクトリパス
+/// - `device`: 走査するファイルシステムのデバイスID。Noneなら制限しない
+///
+/// ## Returns
+/// ディレクトリサイズ or Error
+pub fn get_filesize_on_device<P: AsRef<Path>>(directory: P, device: Option<u64>) -> Result<u64> {
     let mut sum_size = 0;
     let entries = fs::read_dir(directory)?;
 
@@ -92,13 +107,18 @@ pub fn get_filesize<P: AsRef<Path>>(directory: P) -> Result<u64> {
         if metadata.is_file() {
             sum_size += metadata.len();
         } else if metadata.is_dir() {
-            sum_size += get_filesize(entry.path())?;
+            if device.is_some_and(|device| metadata_device(&metadata) != Some(device)) {
+                continue;
+            }
+            sum_size += get_filesize_on_device(entry.path(), device)?;
         }
     }
 
     Ok(sum_size)
 }
 
+// async_recursionが境界をwhere句にも展開する
+#[allow(clippy::multiple_bound_locations)]
 #[async_recursion(?Send)]
 /// ## Summary
 /// async用ファイルサイズ取得関数
@@ -196,13 +216,12 @@ pub async fn get_human_readable_filesize_async<P: AsRef<Path>>(path: P) -> Resul
 pub fn get_metadata<P: AsRef<Path>>(path: P) -> Result<MetaDataInfo> {
     use std::time::SystemTime;
 
-    let metadata = path.as_ref().metadata()?;
-    let size = if metadata.is_dir() {
-        get_filesize(path).unwrap_or_default()
-    } else if metadata.is_file() {
+    let metadata = metadata_or_link(path.as_ref())?;
+    // ディレクトリの合計サイズはフィルターを適用して走査側で求めるので、ここでは再帰しない
+    let size = if metadata.is_file() {
         metadata.len()
     } else {
-        // symbolicは0
+        // ディレクトリとsymbolicは0
         0
     };
     let created = metadata.created().unwrap_or(SystemTime::UNIX_EPOCH);
@@ -221,12 +240,10 @@ pub async fn get_metadata_async<P: AsRef<Path>>(path: P) -> Result<MetaDataInfo>
 
     let p = path.as_ref();
     let metadata = tokio::fs::metadata(p).await?;
-    let size = if metadata.is_dir() {
-        get_filesize(p).unwrap_or_default()
-    } else if metadata.is_file() {
+    let size = if metadata.is_file() {
         metadata.len()
     } else {
-        // symbolicは0
+        // ディレクトリとsymbolicは0
         0
     };
     let created = metadata.created().unwrap_or(SystemTime::UNIX_EPOCH);
@@ -241,13 +258,14 @@ pub async fn get_metadata_async<P: AsRef<Path>>(path: P) -> Result<MetaDataInfo>
 
 #[cfg(unix)]
 pub fn get_metadata<P: AsRef<Path>>(path: P) -> Result<MetaDataInfo> {
-    let metadata = fs::metadata(path)?;
+    let metadata = metadata_or_link(path.as_ref())?;
     Ok(MetaDataInfo {
         size: metadata.len(),
         created: DateTimeWrap::from(metadata.ctime()),
         modified: DateTimeWrap::from(metadata.mtime()),
         owner: metadata.uid(),
         group: metadata.gid(),
+        mode: metadata.mode(),
     })
 }
 
@@ -260,9 +278,199 @@ pub async fn get_metadata_async<P: AsRef<Path>>(path: P) -> Result<MetaDataInfo>
         modified: DateTimeWrap::from(metadata.mtime()),
         owner: metadata.uid(),
         group: metadata.gid(),
+        mode: metadata.mode(),
+    })
+}
+
+/// ## Summary
+/// ソート用のタイムスタンプ
+/// 要求された種類だけを取得し、それ以外や取得できないものはNone
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Timestamps {
+    pub modified: Option<DateTimeWrap>,
+    pub accessed: Option<DateTimeWrap>,
+    pub changed: Option<DateTimeWrap>,
+    pub born: Option<DateTimeWrap>,
+}
+
+impl Timestamps {
+    pub fn get(&self, kind: TimeKind) -> Option<&DateTimeWrap> {
+        match kind {
+            TimeKind::Modified => self.modified.as_ref(),
+            TimeKind::Accessed => self.accessed.as_ref(),
+            TimeKind::Changed => self.changed.as_ref(),
+            TimeKind::Born => self.born.as_ref(),
+        }
+    }
+}
+
+/// ## Summary
+/// 指定した種類のタイムスタンプだけを取得する
+/// 作成日時はLinuxでは`statx`で取得されるため、カーネルやファイルシステムが対応していなければNone
+///
+/// ## Parameters
+/// - `path`: 対象のパス
+/// - `kinds`: 取得するタイムスタンプの種類
+///
+/// ## Returns
+/// タイムスタンプ or メタデータが取得できない場合のError
+pub fn get_timestamps<P: AsRef<Path>>(path: P, kinds: &[TimeKind]) -> Result<Timestamps> {
+    let metadata = metadata_or_link(path.as_ref())?;
+    let mut timestamps = Timestamps::default();
+    for kind in kinds {
+        match kind {
+            TimeKind::Modified => timestamps.modified = metadata.modified().ok().map(Into::into),
+            TimeKind::Accessed => timestamps.accessed = metadata.accessed().ok().map(Into::into),
+            TimeKind::Born => timestamps.born = metadata.created().ok().map(Into::into),
+            TimeKind::Changed => timestamps.changed = changed_time(&metadata),
+        }
+    }
+    Ok(timestamps)
+}
+
+#[cfg(unix)]
+fn changed_time(metadata: &fs::Metadata) -> Option<DateTimeWrap> {
+    Some(DateTimeWrap::from_timestamp(
+        metadata.ctime(),
+        metadata.ctime_nsec() as u32,
+    ))
+}
+
+#[cfg(windows)]
+fn changed_time(_metadata: &fs::Metadata) -> Option<DateTimeWrap> {
+    None
+}
+
+/// ## Summary
+/// リンク先のメタデータを取得する
+/// 壊れたシンボリックリンクはリンク先がないので、リンク自身のメタデータを返す
+fn metadata_or_link(path: &Path) -> io::Result<fs::Metadata> {
+    fs::metadata(path).or_else(|_| fs::symlink_metadata(path))
+}
+
+/// ## Summary
+/// シンボリックリンクのリンク先
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Symlink {
+    /// リンクに書かれているパス。相対パスは解決せずそのまま持つ
+    pub target: String,
+    /// リンク先が存在しない
+    pub broken: bool,
+    /// リンク先が走査中のディレクトリの祖先なので辿らなかった
+    pub recursive: bool,
+}
+
+impl fmt::Display for Symlink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "-> {}", self.target)?;
+        if self.broken {
+            write!(f, " [broken link]")?;
+        }
+        if self.recursive {
+            write!(f, " [recursive, not followed]")?;
+        }
+        Ok(())
+    }
+}
+
+/// ## Summary
+/// パスがシンボリックリンクであればリンク先を読む
+///
+/// ## Returns
+/// リンク先 or シンボリックリンクでない場合はNone
+pub fn read_symlink<P: AsRef<Path>>(path: P) -> Option<Symlink> {
+    let path = path.as_ref();
+    if !path.symlink_metadata().ok()?.file_type().is_symlink() {
+        return None;
+    }
+    let target = fs::read_link(path).ok()?;
+    Some(Symlink {
+        target: target.to_string_lossy().into_owned(),
+        broken: fs::metadata(path).is_err(),
+        recursive: false,
     })
 }
 
+/// ## Summary
+/// パスがあるファイルシステムのデバイスID(`st_dev`)を取得する
+/// シンボリックリンクはリンク先のデバイスを返す
+pub fn device_id<P: AsRef<Path>>(path: P) -> Option<u64> {
+    fs::metadata(path)
+        .ok()
+        .and_then(|metadata| metadata_device(&metadata))
+}
+
+#[cfg(unix)]
+fn metadata_device(metadata: &fs::Metadata) -> Option<u64> {
+    Some(metadata.dev())
+}
+
+/// ## Note
+/// Windowsの安定版APIではボリュームを取得できないので、常にNoneを返す
+#[cfg(windows)]
+fn metadata_device(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// ## Summary
+/// ファイルを一意に識別するID
+/// unixではデバイス番号とinode番号の組で、同じディレクトリに別のパスから辿り着いても同じ値になる
+#[cfg(unix)]
+pub type FileId = (u64, u64);
+
+/// ## Summary
+/// ファイルを一意に識別するID
+/// inode番号がないので、シンボリックリンクを解決した絶対パスで代用する
+#[cfg(windows)]
+pub type FileId = std::path::PathBuf;
+
+/// ## Summary
+/// リンクを辿った先のファイルのIDを取得する
+#[cfg(unix)]
+pub fn file_id<P: AsRef<Path>>(path: P) -> io::Result<FileId> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.dev(), metadata.ino()))
+}
+
+/// ## Summary
+/// リンクを辿った先のファイルのIDを取得する
+#[cfg(windows)]
+pub fn file_id<P: AsRef<Path>>(path: P) -> io::Result<FileId> {
+    path.as_ref().canonicalize()
+}
+
+/// ## Summary
+/// ファイルモードを`ls -l`と同じ`drwxr-xr-x`形式にする
+#[cfg(unix)]
+pub fn format_permissions(mode: u32) -> String {
+    let kind = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o020000 => 'c',
+        0o060000 => 'b',
+        0o010000 => 'p',
+        0o140000 => 's',
+        _ => '-',
+    };
+    let mut prot = String::from(kind);
+    for (shift, special, lower, upper) in [
+        (6, 0o4000, 's', 'S'),
+        (3, 0o2000, 's', 'S'),
+        (0, 0o1000, 't', 'T'),
+    ] {
+        let bits = (mode >> shift) & 0o7;
+        prot.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        prot.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        prot.push(match (mode & special != 0, bits & 0o1 != 0) {
+            (true, true) => lower,
+            (true, false) => upper,
+            (false, true) => 'x',
+            (false, false) => '-',
+        });
+    }
+    prot
+}
+
 impl fmt::Display for MetaDataInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         #[cfg(unix)]
@@ -304,9 +512,85 @@ impl Serialize for MetaDataInfo {
 mod test {
     use super::*;
 
-    // #[test]
-    // fn get_filename_returns_filename() {
-    //     let fname = get_filename(".");
-    //     assert_eq!("rxtree", fname);
-    // }
+    #[test]
+    fn get_filename_returns_filename() {
+        let fname = get_filename("src/main.rs");
+        assert_eq!("main.rs", fname);
+    }
+
+    #[test]
+    fn get_timestamps_fetches_requested_kinds_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a");
+        fs::write(&file, "").unwrap();
+        let timestamps = get_timestamps(&file, &[TimeKind::Modified]).unwrap();
+        assert!(timestamps.modified.is_some());
+        assert!(timestamps.accessed.is_none());
+        assert!(timestamps.changed.is_none());
+        assert!(timestamps.born.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn format_permissions_like_ls() {
+        assert_eq!(format_permissions(0o040755), "drwxr-xr-x");
+        assert_eq!(format_permissions(0o104755), "-rwsr-xr-x");
+        assert_eq!(format_permissions(0o041777), "drwxrwxrwt");
+        assert_eq!(format_permissions(0o100600), "-rw-------");
+    }
+
+    #[test]
+    fn get_filesize_on_device_skips_other_devices() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a"), "12").unwrap();
+        fs::write(dir.path().join("sub/b"), "345").unwrap();
+
+        let device = device_id(dir.path());
+        assert_eq!(get_filesize_on_device(dir.path(), device).unwrap(), 5);
+        #[cfg(unix)]
+        assert_eq!(
+            get_filesize_on_device(dir.path(), Some(u64::MAX)).unwrap(),
+            2
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn read_symlink_detects_broken_links() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), "").unwrap();
+        std::os::unix::fs::symlink("file", dir.path().join("link")).unwrap();
+        std::os::unix::fs::symlink("missing", dir.path().join("dangling")).unwrap();
+
+        assert_eq!(read_symlink(dir.path().join("file")), None);
+        let link = read_symlink(dir.path().join("link")).unwrap();
+        assert_eq!(link.target, "file");
+        assert!(!link.broken);
+        let dangling = read_symlink(dir.path().join("dangling")).unwrap();
+        assert!(dangling.broken);
+        assert_eq!(dangling.to_string(), "-> missing [broken link]");
+        // 壊れたリンクでもリンク自身の情報は取得できる
+        assert!(get_metadata(dir.path().join("dangling")).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_id_follows_links() {
+        let dir = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(".", dir.path().join("self")).unwrap();
+        assert_eq!(
+            file_id(dir.path()).unwrap(),
+            file_id(dir.path().join("self")).unwrap()
+        );
+    }
+
+    #[test]
+    fn get_filename_resolves_parent_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let expected = dir.path().canonicalize().unwrap();
+        let fname = get_filename(dir.path().join("sub/.."));
+        assert_eq!(expected.file_name().unwrap().to_string_lossy(), fname);
+    }
 }
@@ -74,13 +74,15 @@ impl Eq for Unit {}
 
 impl PartialOrd for Unit {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.to_bytes_f64().partial_cmp(&other.to_bytes_f64())
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Unit {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         // TODO f64の比較はNanがあるとpaincを起こすのでEqualで一応対策
-        self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Equal)
+        self.to_bytes_f64()
+            .partial_cmp(&other.to_bytes_f64())
+            .unwrap_or(std::cmp::Ordering::Equal)
     }
 }
@@ -0,0 +1,146 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use async_recursion::async_recursion;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{tree::TreeNode, utils::files::FileId};
+
+use super::{Entry, Pipeline, Visited, Walker};
+
+/// ## Summary
+/// tokioのタスクとしてディレクトリを並行に読み込むWalker
+/// ファイルシステム・gitへのアクセスは`spawn_blocking`で行い、同時に処理するエントリの数はセマフォで`jobs`個までに制限する
+#[derive(Debug)]
+pub struct AsyncWalker {
+    pipeline: Arc<Pipeline>,
+    semaphore: Arc<Semaphore>,
+}
+
+impl AsyncWalker {
+    pub fn new(pipeline: Pipeline, jobs: usize) -> Self {
+        Self {
+            pipeline: Arc::new(pipeline),
+            semaphore: Arc::new(Semaphore::new(jobs.max(1))),
+        }
+    }
+
+    /// ## Summary
+    /// 既にtokioのランタイム上にいる場合はこちらを使う
+    pub async fn walk_async(&self, root: &Path) -> Option<TreeNode> {
+        let permit = self.semaphore.clone().acquire_owned().await.ok()?;
+        walk_entry(
+            self.pipeline.clone(),
+            self.semaphore.clone(),
+            permit,
+            root.to_path_buf(),
+            1,
+            vec![],
+        )
+        .await
+        .into_node()
+    }
+}
+
+impl Walker for AsyncWalker {
+    fn walk(&self, root: &Path) -> Option<TreeNode> {
+        let runtime = tokio::runtime::Runtime::new()
+            .map_err(|e| eprintln!("ERROR: {}", e))
+            .ok()?;
+        runtime.block_on(self.walk_async(root))
+    }
+}
+
+/// ## Summary
+/// 子ごとにタスクをspawnし、spawnした順にjoinすることで子の順序を保つ
+///
+/// ## Parameters
+/// - `permit`: 呼び出し側がspawnする前に取得したpermit。エントリの読み込みが終わると返す
+///
+/// ## Note
+/// permitはブロッキング処理の間だけ保持する
+/// 子の完了を待つ間も保持すると、深い階層で全permitを親が握ったままデッドロックする
+/// 子のタスクはpermitを取得してからspawnするので、読み込み中のエントリは`jobs`個を超えない
+#[async_recursion]
+async fn walk_entry(
+    pipeline: Arc<Pipeline>,
+    semaphore: Arc<Semaphore>,
+    permit: OwnedSemaphorePermit,
+    path: PathBuf,
+    depth: u32,
+    ancestors: Vec<FileId>,
+) -> Visited {
+    let (entry, paths) = match read_entry(pipeline.clone(), permit, path, depth, ancestors).await {
+        Ok(read) => read,
+        Err(bytes) => return Visited::Hidden(bytes),
+    };
+
+    let mut handles = Vec::with_capacity(paths.len());
+    for child in paths {
+        let Ok(permit) = semaphore.clone().acquire_owned().await else {
+            return Visited::Hidden(0);
+        };
+        handles.push(tokio::spawn(walk_entry(
+            pipeline.clone(),
+            semaphore.clone(),
+            permit,
+            child,
+            depth + 1,
+            entry.ancestors.clone(),
+        )));
+    }
+
+    let mut children = vec![];
+    for handle in handles {
+        // フィルターで除外された子はスキップするだけで、ディレクトリごと落とさない
+        match handle.await {
+            Ok(visited) => children.push(visited),
+            Err(e) => eprintln!("ERROR: {}", e),
+        }
+    }
+
+    let Ok(permit) = semaphore.acquire_owned().await else {
+        return Visited::Hidden(0);
+    };
+    tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        pipeline.finish(entry, children)
+    })
+    .await
+    .unwrap_or_else(|e| {
+        eprintln!("ERROR: {}", e);
+        Visited::Hidden(0)
+    })
+}
+
+/// ## Summary
+/// フィルターを適用し、ディレクトリであれば直下のエントリを読み込む
+///
+/// ## Returns
+/// Entryと子のパス or 除外された・読み込めなかった場合に親のサイズに加算するバイト数
+async fn read_entry(
+    pipeline: Arc<Pipeline>,
+    permit: OwnedSemaphorePermit,
+    path: PathBuf,
+    depth: u32,
+    ancestors: Vec<FileId>,
+) -> Result<(Entry, Vec<PathBuf>), u64> {
+    tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        let entry = pipeline.accept(&path, depth, &ancestors)?;
+        if !entry.is_dir {
+            return Ok((entry, vec![]));
+        }
+        match pipeline.read_dir(&entry) {
+            Some(paths) => Ok((entry, paths)),
+            None => Err(0),
+        }
+    })
+    .await
+    .unwrap_or_else(|e| {
+        eprintln!("ERROR: {}", e);
+        Err(0)
+    })
+}
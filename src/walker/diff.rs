@@ -0,0 +1,434 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use git2::{ObjectType, Oid, Repository, TreeEntry, TreeWalkMode, TreeWalkResult};
+
+use crate::{
+    git::{
+        diff::{diff_trees, DiffStatus, GitDiff},
+        object::GitObject,
+    },
+    tree::TreeNode,
+    utils,
+};
+
+use super::{
+    rev::{blob_size, entry_bytes, open_repository_at, resolve_tree, sorted_entries},
+    Entry, Pipeline, Visited, Walker,
+};
+
+/// ## Summary
+/// 2つのリビジョンのツリーを重ね合わせて走査し、変更を付与するWalker
+/// どちらか一方にしかないエントリも表示する
+pub struct DiffWalker {
+    pipeline: Pipeline,
+    repo: Repository,
+    old: Oid,
+    new: Oid,
+    changes: HashMap<PathBuf, GitDiff>,
+    /// リネーム前のパス。リネーム後のエントリとして表示するので削除としては表示しない
+    renamed_from: HashSet<PathBuf>,
+}
+
+impl DiffWalker {
+    /// ## Summary
+    /// `old`と`new`のツリーのうち、走査を開始するパスに対応する部分を比較する
+    ///
+    /// ## Returns
+    /// DiffWalker or リポジトリが見つからない・リビジョンが解決できない場合のError
+    pub fn open(pipeline: Pipeline, old: &str, new: &str) -> Result<Self> {
+        let path = pipeline.options().path.clone();
+        let repo = open_repository_at(&path)?;
+        let old = resolve_tree(&repo, &path, old)?;
+        let new = resolve_tree(&repo, &path, new)?;
+        let changes = diff_trees(&repo, &repo.find_tree(old)?, &repo.find_tree(new)?)
+            .map_err(|e| anyhow!("cannot diff {}: {}", path.display(), e.message()))?;
+        let renamed_from = changes
+            .values()
+            .filter_map(|change| change.old_path.as_ref().map(PathBuf::from))
+            .collect();
+
+        Ok(Self {
+            pipeline,
+            repo,
+            old,
+            new,
+            changes,
+            renamed_from,
+        })
+    }
+
+    /// ## Parameters
+    /// - `old`・`new`: それぞれのリビジョンでのエントリ。少なくとも一方はSome
+    /// - `relative`: 比較するツリーからの相対パス
+    fn visit_entry(
+        &self,
+        old: Option<&TreeEntry>,
+        new: Option<&TreeEntry>,
+        relative: &Path,
+        path: &Path,
+        depth: u32,
+    ) -> Visited {
+        // 両方にある場合は新しい方の種類で表示する
+        let Some(tree_entry) = new.or(old) else {
+            return Visited::Hidden(0);
+        };
+        let is_tree = |entry: Option<&TreeEntry>| {
+            entry.is_some_and(|entry| entry.kind() == Some(ObjectType::Tree))
+        };
+        let is_dir = is_tree(Some(tree_entry));
+        if self.pipeline.is_too_deep(depth) {
+            // 深さで切り捨てたエントリも親ディレクトリの中身なので常にサイズに含める
+            return Visited::Hidden(self.hidden(tree_entry, true));
+        }
+        let name = String::from_utf8_lossy(tree_entry.name_bytes()).into_owned();
+        if self.pipeline.is_filtered(path, &name, is_dir, depth) {
+            return Visited::Hidden(self.hidden(tree_entry, self.pipeline.counts_filtered_size()));
+        }
+
+        let children = if is_dir {
+            let old = old.filter(|old| is_tree(Some(old))).map(TreeEntry::id);
+            self.visit_children(old, new.map(TreeEntry::id), relative, path, depth)
+        } else {
+            vec![]
+        };
+        let bytes = self.bytes(tree_entry, &children);
+
+        let entry = Entry {
+            path: path.to_path_buf(),
+            name,
+            depth,
+            is_dir,
+            ..Default::default()
+        };
+        let mut visited = self.pipeline.assemble(entry, children, bytes, None);
+        if let Visited::Node(node) = &mut visited {
+            node.git_object = Some(GitObject::from_entry(&self.repo, tree_entry));
+            node.diff = if is_dir {
+                let status = match (old, new) {
+                    (None, _) => Some(DiffStatus::Added),
+                    (_, None) => Some(DiffStatus::Deleted),
+                    (old, _) if !is_tree(old) => Some(DiffStatus::TypeChange),
+                    _ => None,
+                };
+                roll_up(node, status)
+            } else {
+                self.changes.get(relative).cloned()
+            };
+        }
+        visited
+    }
+
+    /// ## Summary
+    /// 両方のツリーの子を名前順に重ね合わせて走査する
+    fn visit_children(
+        &self,
+        old: Option<Oid>,
+        new: Option<Oid>,
+        relative: &Path,
+        path: &Path,
+        depth: u32,
+    ) -> Vec<Visited> {
+        let mut entries: BTreeMap<Vec<u8>, (Option<TreeEntry>, Option<TreeEntry>)> =
+            BTreeMap::new();
+        for (id, is_new) in [(old, false), (new, true)] {
+            let Some(id) = id else {
+                continue;
+            };
+            let tree = match self.repo.find_tree(id) {
+                Ok(tree) => tree,
+                Err(e) => {
+                    eprintln!("ERROR: {}", e.message());
+                    continue;
+                }
+            };
+            for entry in sorted_entries(&tree) {
+                let slot = entries.entry(entry.name_bytes().to_vec()).or_default();
+                if is_new {
+                    slot.1 = Some(entry);
+                } else {
+                    slot.0 = Some(entry);
+                }
+            }
+        }
+
+        entries
+            .into_iter()
+            .filter_map(|(name, (old, new))| {
+                let name = String::from_utf8_lossy(&name).into_owned();
+                let relative = relative.join(&name);
+                if new.is_none() && self.is_renamed_away(old.as_ref(), &relative) {
+                    return None;
+                }
+                Some(self.visit_entry(
+                    old.as_ref(),
+                    new.as_ref(),
+                    &relative,
+                    &path.join(&name),
+                    depth + 1,
+                ))
+            })
+            .collect()
+    }
+
+    /// ## Summary
+    /// 古いリビジョンにしかないエントリが、すべて別のパスへリネームされたか
+    /// 中身がすべてリネームされたディレクトリは、空の削除として表示しない
+    fn is_renamed_away(&self, old: Option<&TreeEntry>, relative: &Path) -> bool {
+        if self.renamed_from.contains(relative) {
+            return true;
+        }
+        let Some(tree) = old
+            .filter(|old| old.kind() == Some(ObjectType::Tree) && !self.renamed_from.is_empty())
+            .and_then(|old| self.repo.find_tree(old.id()).ok())
+        else {
+            return false;
+        };
+
+        let mut renamed = true;
+        // 途中で打ち切った場合もErrになるので、結果は`renamed`で判断する
+        let _ = tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.kind() == Some(ObjectType::Tree) {
+                return TreeWalkResult::Ok;
+            }
+            let name = String::from_utf8_lossy(entry.name_bytes());
+            if self
+                .renamed_from
+                .contains(&relative.join(dir).join(name.as_ref()))
+            {
+                TreeWalkResult::Ok
+            } else {
+                renamed = false;
+                TreeWalkResult::Abort
+            }
+        });
+        renamed
+    }
+
+    fn bytes(&self, tree_entry: &TreeEntry, children: &[Visited]) -> Option<u64> {
+        self.pipeline.options().size.as_ref()?;
+        match tree_entry.kind() {
+            Some(ObjectType::Tree) => Some(children.iter().map(Visited::bytes).sum()),
+            Some(ObjectType::Blob) => Some(blob_size(&self.repo, tree_entry.id())),
+            _ => Some(0),
+        }
+    }
+
+    fn hidden(&self, tree_entry: &TreeEntry, count_size: bool) -> u64 {
+        if self.pipeline.options().size.is_none() || !count_size {
+            return 0;
+        }
+        entry_bytes(&self.repo, tree_entry)
+    }
+}
+
+/// ## Summary
+/// 子の変更行数を合計してディレクトリの変更を作る
+/// ディレクトリ自体の追加・削除・種類の変更がなければ、子に変更がある場合だけ変更ありとする
+fn roll_up(node: &TreeNode, status: Option<DiffStatus>) -> Option<GitDiff> {
+    let changed: Vec<&GitDiff> = node
+        .get_children()
+        .iter()
+        .flatten()
+        .filter_map(|child| child.get_diff())
+        .collect();
+    let status = status.or_else(|| (!changed.is_empty()).then_some(DiffStatus::Modified))?;
+
+    let mut diff = GitDiff::new(status);
+    for change in changed {
+        diff.additions += change.additions;
+        diff.deletions += change.deletions;
+    }
+    Some(diff)
+}
+
+impl Walker for DiffWalker {
+    fn walk(&self, root: &Path) -> Option<TreeNode> {
+        let children = self.visit_children(Some(self.old), Some(self.new), Path::new(""), root, 1);
+        let bytes = self
+            .pipeline
+            .options()
+            .size
+            .as_ref()
+            .map(|_| children.iter().map(Visited::bytes).sum());
+
+        let entry = Entry {
+            path: root.to_path_buf(),
+            name: utils::files::get_filename(root),
+            depth: 1,
+            is_dir: true,
+            ..Default::default()
+        };
+        let mut node = self
+            .pipeline
+            .assemble(entry, children, bytes, None)
+            .into_node()?;
+        node.git_object = Some(GitObject::tree(self.new));
+        node.diff = roll_up(&node, None);
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use git2::Signature;
+
+    use super::*;
+    use crate::{build_tree, TreeOptions};
+
+    fn child<'a>(node: &'a TreeNode, name: &str) -> &'a TreeNode {
+        node.get_children()
+            .iter()
+            .flatten()
+            .find(|child| child.get_name() == name)
+            .unwrap()
+    }
+
+    fn names(node: &TreeNode) -> Vec<&str> {
+        node.get_children()
+            .iter()
+            .flatten()
+            .map(|child| child.get_name())
+            .collect()
+    }
+
+    fn commit(repo: &Repository, message: &str) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.update_all(["*"].iter(), None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    /// ## Summary
+    /// 変更・リネーム・削除・追加を含む2つのコミットを持つリポジトリ
+    fn repository() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("README.md"), "readme\n").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {\n}\n").unwrap();
+        fs::write(
+            root.join("src/old.rs"),
+            "pub fn old() {\n    println!(\"old\");\n}\n",
+        )
+        .unwrap();
+        fs::write(root.join("docs/guide.md"), "one\ntwo\n").unwrap();
+        commit(&repo, "first");
+
+        fs::write(root.join("src/main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        fs::rename(root.join("src/old.rs"), root.join("src/new.rs")).unwrap();
+        fs::remove_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("added.txt"), "a\nb\nc\n").unwrap();
+        commit(&repo, "second");
+        dir
+    }
+
+    #[test]
+    fn test_diff_tree() {
+        let dir = repository();
+        let options = TreeOptions::new(dir.path()).diff("HEAD~1", "HEAD");
+        let root = build_tree(&options).unwrap();
+
+        assert_eq!(names(&root), ["README.md", "added.txt", "docs", "src"]);
+        assert_eq!(child(&root, "README.md").get_diff(), None);
+
+        let added = child(&root, "added.txt").get_diff().unwrap();
+        assert_eq!(added.status, DiffStatus::Added);
+        assert_eq!((added.additions, added.deletions), (3, 0));
+
+        let docs = child(&root, "docs");
+        assert_eq!(docs.get_diff().unwrap().status, DiffStatus::Deleted);
+        assert_eq!(names(docs), ["guide.md"]);
+        assert_eq!(
+            child(docs, "guide.md").get_diff().unwrap().status,
+            DiffStatus::Deleted
+        );
+
+        let src = child(&root, "src");
+        assert_eq!(names(src), ["main.rs", "new.rs"]);
+        let main = child(src, "main.rs").get_diff().unwrap();
+        assert_eq!(main.status, DiffStatus::Modified);
+        assert_eq!((main.additions, main.deletions), (1, 0));
+        let renamed = child(src, "new.rs").get_diff().unwrap();
+        assert_eq!(renamed.status, DiffStatus::Renamed);
+        assert_eq!(renamed.old_path.as_deref(), Some("src/old.rs"));
+
+        let total = root.get_diff().unwrap();
+        assert_eq!(total.status, DiffStatus::Modified);
+        assert_eq!((total.additions, total.deletions), (4, 2));
+    }
+
+    #[test]
+    fn test_directory_renamed_away_is_omitted() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        fs::create_dir_all(root.join("legacy/nested")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(
+            root.join("legacy/nested/only.rs"),
+            "pub fn only() {}\n".repeat(5),
+        )
+        .unwrap();
+        commit(&repo, "first");
+
+        fs::rename(root.join("legacy/nested/only.rs"), root.join("src/only.rs")).unwrap();
+        fs::remove_dir_all(root.join("legacy")).unwrap();
+        commit(&repo, "second");
+
+        let options = TreeOptions::new(root).diff("HEAD~1", "HEAD");
+        let tree = build_tree(&options).unwrap();
+
+        // 中身がすべて移動したディレクトリは空の削除として残さない
+        assert_eq!(names(&tree), ["src"]);
+        let only = child(child(&tree, "src"), "only.rs").get_diff().unwrap();
+        assert_eq!(only.status, DiffStatus::Renamed);
+        assert_eq!(only.old_path.as_deref(), Some("legacy/nested/only.rs"));
+    }
+
+    #[test]
+    fn test_diff_subdirectory() {
+        let dir = repository();
+        let options = TreeOptions::new(dir.path().join("src")).diff("HEAD~1", "HEAD");
+        let root = build_tree(&options).unwrap();
+
+        assert_eq!(names(&root), ["main.rs", "new.rs"]);
+        let renamed = child(&root, "new.rs").get_diff().unwrap();
+        assert_eq!(renamed.old_path.as_deref(), Some("old.rs"));
+    }
+
+    #[test]
+    fn test_diff_json() {
+        let dir = repository();
+        let options = TreeOptions::new(dir.path()).diff("HEAD~1", "HEAD");
+        let root = build_tree(&options).unwrap();
+        let json = serde_json::to_value(child(&root, "added.txt")).unwrap();
+
+        assert_eq!(json["diff"]["status"], "added");
+        assert_eq!(json["diff"]["additions"], 3);
+        assert_eq!(json["diff"]["deletions"], 0);
+    }
+}
@@ -0,0 +1,1204 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use crate::{
+    filter::{ext::ExtensionSet, gitignore::GitIgnoreFilter, pattern::PatternSet},
+    foramt::{mode::Mode, sizeformat::SizeFormat},
+    git::{
+        churn::Churn,
+        log::CommitInfo,
+        status::GitStatus,
+        submodule::{self, RepoBoundary},
+    },
+    ndjson::NdjsonSink,
+    tree::{TreeNode, TreeOptions},
+    utils::{
+        self,
+        datetime::TimeKind,
+        files::{FileId, MetaDataInfo, Symlink},
+        size,
+    },
+};
+
+pub mod async_walker;
+pub mod diff;
+pub mod parallel;
+pub mod rev;
+pub mod sync;
+
+pub use async_walker::AsyncWalker;
+pub use diff::DiffWalker;
+pub use parallel::ParallelWalker;
+pub use rev::RevWalker;
+pub use sync::SyncWalker;
+
+/// ## Summary
+/// ディレクトリを走査してTreeNodeを構築する
+/// 同期・並列・非同期の各実装は`Pipeline`を共有し、走査の方法だけが異なる
+pub trait Walker {
+    /// ## Summary
+    /// `root`から走査を開始する
+    ///
+    /// ## Returns
+    /// ルートのTreeNode or ルートがフィルターで除外された・`--ndjson`で書き出した場合はNone
+    fn walk(&self, root: &Path) -> Option<TreeNode>;
+}
+
+/// ## Summary
+/// 実行モードに対応するWalkerを返す
+///
+/// ## Parameters
+/// - `jobs`: 並列・非同期モードで同時に処理する数。同期モードでは無視される
+pub fn walker_for(mode: &Mode, pipeline: Pipeline, jobs: usize) -> Box<dyn Walker> {
+    match mode {
+        Mode::Sync => Box::new(SyncWalker::new(pipeline)),
+        Mode::Parallel => Box::new(ParallelWalker::new(pipeline, jobs)),
+        Mode::Async => Box::new(AsyncWalker::new(pipeline, jobs)),
+    }
+}
+
+/// ## Summary
+/// 走査中の1エントリ
+/// フィルターを通過したものだけが作られる
+#[derive(Debug, Default)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub name: String,
+    pub depth: u32,
+    pub is_dir: bool,
+    /// シンボリックリンクの場合のリンク先
+    pub symlink: Option<Symlink>,
+    /// `--follow`で循環を検出するための、ルートからこのディレクトリまでのID
+    /// `--follow`が指定されていない場合は空
+    pub ancestors: Vec<FileId>,
+    /// `--one-file-system`で辿らない、別のファイルシステムのマウントポイント
+    pub mount_point: bool,
+}
+
+/// ## Summary
+/// 全Walkerで共有するフィルター・メタデータ・サイズの処理
+#[derive(Debug)]
+pub struct Pipeline {
+    options: TreeOptions,
+    git_statuses: HashMap<PathBuf, GitStatus>,
+    /// `--git-changed`で表示するパス。変更のあるファイルとその祖先のディレクトリ
+    git_changed: Option<HashSet<PathBuf>>,
+    /// `--git-log`で表示する最後に変更したコミット
+    last_commits: HashMap<PathBuf, CommitInfo>,
+    /// `--git-churn`で表示する履歴での変更回数と行数
+    churn: HashMap<PathBuf, Churn>,
+    /// サブモジュールのルート
+    submodules: HashMap<PathBuf, RepoBoundary>,
+    gitignore: Option<GitIgnoreFilter>,
+    ignores: PatternSet,
+    includes: Option<PatternSet>,
+    extensions: ExtensionSet,
+    exclude_extensions: ExtensionSet,
+    time_kinds: Vec<TimeKind>,
+    root_abs: PathBuf,
+    /// `--one-file-system`で走査するルートのデバイスID
+    root_device: Option<u64>,
+    /// `--ndjson`で走査しながらエントリを書き出す出力先
+    sink: Option<NdjsonSink>,
+}
+
+impl Pipeline {
+    /// ## Returns
+    /// Pipeline or `--ignore`・`--include`のパターンが不正な場合のError
+    pub fn new(options: TreeOptions, git_statuses: HashMap<PathBuf, GitStatus>) -> Result<Self> {
+        // 指定がなければリポジトリの中でだけ.gitignoreを適用する
+        let gitignore = match options.gitignore {
+            Some(false) => None,
+            Some(true) => GitIgnoreFilter::discover(&options.path)
+                .or_else(|| Some(GitIgnoreFilter::new(&options.path))),
+            None => GitIgnoreFilter::discover(&options.path),
+        };
+
+        let compile = |patterns: &[String]| {
+            if options.regex {
+                PatternSet::regex(patterns)
+            } else {
+                PatternSet::glob(patterns)
+            }
+        };
+        let ignores = compile(&options.ignores)?;
+        let includes = if options.includes.is_empty() {
+            None
+        } else {
+            Some(compile(&options.includes)?)
+        };
+
+        let git_changed = options.git_changed.as_ref().map(|classes| {
+            let mut paths = HashSet::new();
+            for (path, status) in &git_statuses {
+                if !status.is_in(classes) {
+                    continue;
+                }
+                for ancestor in path.ancestors() {
+                    if !paths.insert(ancestor.to_path_buf()) {
+                        break;
+                    }
+                }
+            }
+            paths
+        });
+
+        // 履歴は表示するリビジョン(比較先)から辿る
+        let rev = options
+            .diff
+            .as_ref()
+            .map(|(_, new)| new.as_str())
+            .or(options.rev.as_deref());
+        let last_commits = if options.git_log {
+            crate::git::log::last_commits(&options.path, rev)
+        } else {
+            HashMap::new()
+        };
+        let churn = if options.git_churn {
+            crate::git::churn::churn(&options.path, options.since, rev)
+        } else {
+            HashMap::new()
+        };
+
+        let submodules = if options.git_integration {
+            submodule::submodules(&options.path)
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self {
+            extensions: ExtensionSet::new(&options.extensions),
+            exclude_extensions: ExtensionSet::new(&options.exclude_extensions),
+            time_kinds: options.sort.time_kinds(),
+            root_device: if options.one_file_system {
+                utils::files::device_id(&options.path)
+            } else {
+                None
+            },
+            root_abs: options
+                .path
+                .canonicalize()
+                .unwrap_or_else(|_| options.path.clone()),
+            options,
+            git_statuses,
+            git_changed,
+            last_commits,
+            churn,
+            submodules,
+            gitignore,
+            ignores,
+            includes,
+            sink: None,
+        })
+    }
+
+    /// ## Summary
+    /// 走査したエントリをツリーに残さず`sink`に書き出すようにする
+    pub fn with_sink(mut self, sink: NdjsonSink) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// ## Summary
+    /// 深さ・隠しファイル・除外パターン・gitignore・包含パターン・拡張子のフィルターを適用する
+    /// ルートは明示的に指定されたものなので名前によるフィルターは適用しない
+    ///
+    /// ## Parameters
+    /// - `ancestors`: 親ディレクトリの`Entry::ancestors`。ルートの場合は空
+    ///
+    /// ## Returns
+    /// フィルターを通過した場合はEntry, 除外された場合は親のサイズに加算するバイト数
+    pub fn accept(&self, path: &Path, depth: u32, ancestors: &[FileId]) -> Result<Entry, u64> {
+        if self.is_too_deep(depth) {
+            // 深さで切り捨てたエントリも親ディレクトリの中身なのでサイズに含める
+            return Err(self.truncated(path, depth));
+        }
+        let name = utils::files::get_filename(path);
+        let mut symlink = utils::files::read_symlink(path);
+        // ルートは明示的に指定されたものなのでリンクでも辿る
+        let follow = self.options.follow || depth == 1;
+        let mut is_dir = (symlink.is_none() || follow) && path.is_dir();
+        // マウントポイントはディレクトリとして扱うが、中は読まない
+        let mount_point = is_dir && depth > 1 && self.is_other_device(path);
+
+        let mut ancestors = ancestors.to_vec();
+        if is_dir && self.options.follow {
+            match utils::files::file_id(path) {
+                Ok(id) if ancestors.contains(&id) => {
+                    // 祖先に戻るリンクを辿ると終わらないので、ファイルとして表示する
+                    is_dir = false;
+                    if let Some(symlink) = &mut symlink {
+                        symlink.recursive = true;
+                    }
+                }
+                Ok(id) => ancestors.push(id),
+                Err(e) => eprintln!("ERROR: {}", e),
+            }
+        }
+
+        if self.is_filtered(path, &name, is_dir, depth) {
+            return Err(self.hidden(path, self.options.size_filtered));
+        }
+
+        Ok(Entry {
+            path: path.to_path_buf(),
+            name,
+            depth,
+            is_dir,
+            symlink,
+            ancestors,
+            mount_point,
+        })
+    }
+
+    /// ## Summary
+    /// `--one-file-system`でルートと別のファイルシステムにあるか
+    fn is_other_device(&self, path: &Path) -> bool {
+        self.root_device.is_some_and(|root_device| {
+            utils::files::device_id(path).is_some_and(|device| device != root_device)
+        })
+    }
+
+    /// ## Summary
+    /// `--depth`より深いエントリか
+    pub fn is_too_deep(&self, depth: u32) -> bool {
+        self.options
+            .max_depth
+            .is_some_and(|max_depth| depth > max_depth)
+    }
+
+    /// ## Summary
+    /// 名前によるフィルターで除外されるか
+    /// ディスク以外から作るツリーでも同じ規則を適用できるよう、種類は呼び出し側が渡す
+    pub fn is_filtered(&self, path: &Path, name: &str, is_dir: bool, depth: u32) -> bool {
+        depth > 1 && !self.name_filter(path, name, is_dir)
+    }
+
+    /// ## Summary
+    /// フィルターで除外したエントリもディレクトリのサイズに含めるか
+    pub fn counts_filtered_size(&self) -> bool {
+        self.options.size.is_some() && self.options.size_filtered
+    }
+
+    pub fn options(&self) -> &TreeOptions {
+        &self.options
+    }
+
+    /// ## Summary
+    /// ツリーに表示しないエントリのサイズを求める
+    /// 表示しないディレクトリは走査しないので、ここでだけ再帰的に読み込む
+    fn hidden(&self, path: &Path, count_size: bool) -> u64 {
+        if self.options.size.is_none() || !count_size {
+            return 0;
+        }
+
+        let bytes = match path.symlink_metadata() {
+            Ok(metadata) if metadata.is_dir() => {
+                utils::files::get_filesize_on_device(path, self.root_device)
+            }
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) => Err(e.into()),
+        };
+        bytes.map_err(|e| eprintln!("ERROR: {}", e)).unwrap_or(0)
+    }
+
+    /// ## Summary
+    /// `--depth`で切り捨てたエントリのサイズを求める
+    /// 切り捨てなかった場合と合計が変わらないよう、`--size-filtered`でなければ同じフィルターで除外したものは数えない
+    fn truncated(&self, path: &Path, depth: u32) -> u64 {
+        if self.options.size.is_none() {
+            return 0;
+        }
+        if self.options.size_filtered {
+            return self.hidden(path, true);
+        }
+
+        let metadata = match path.symlink_metadata() {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                eprintln!("ERROR: {}", e);
+                return 0;
+            }
+        };
+        let is_dir = metadata.is_dir();
+        if self.is_filtered(path, &utils::files::get_filename(path), is_dir, depth) {
+            return 0;
+        }
+        if !is_dir {
+            return metadata.len();
+        }
+        // マウントポイントは走査した場合も中を読まない
+        if self.is_other_device(path) {
+            return 0;
+        }
+        let bytes = match fs::read_dir(path) {
+            Ok(entries) => entries
+                .filter_map(Result::ok)
+                .map(|entry| self.truncated(&entry.path(), depth + 1))
+                .sum(),
+            Err(e) => {
+                eprintln!("ERROR: {}", e);
+                0
+            }
+        };
+        self.forget_ignore_rules(path);
+        bytes
+    }
+
+    /// ## Summary
+    /// 走査を終えたディレクトリについて、読み込んだignoreファイルのルールを捨てる
+    fn forget_ignore_rules(&self, dir: &Path) {
+        if let Some(gitignore) = &self.gitignore {
+            gitignore.forget(dir);
+        }
+    }
+
+    fn name_filter(&self, path: &Path, name: &str, is_dir: bool) -> bool {
+        // -aが指定されてないかつ隠しフォルダーならskip
+        if !self.options.is_show_hidden && name.starts_with('.') {
+            return false;
+        }
+
+        let relative = path.strip_prefix(&self.options.path).unwrap_or(path);
+        if self.ignores.is_match(name, relative) {
+            return false;
+        }
+
+        if let Some(gitignore) = &self.gitignore {
+            if gitignore.is_ignored(path, is_dir) {
+                return false;
+            }
+        }
+
+        if let Some(git_changed) = &self.git_changed {
+            if !git_changed.contains(&self.absolute(path)) {
+                return false;
+            }
+        }
+
+        // 包含パターンと拡張子はファイルにだけ適用し、ディレクトリは常に辿る
+        if is_dir {
+            return true;
+        }
+
+        if let Some(includes) = &self.includes {
+            if !includes.is_match(name, relative) {
+                return false;
+            }
+        }
+
+        self.ext_filter(name)
+    }
+
+    fn ext_filter(&self, name: &str) -> bool {
+        if !self.extensions.is_empty() && !self.extensions.matches(name) {
+            return false;
+        }
+
+        !self.exclude_extensions.matches(name)
+    }
+
+    /// ## Summary
+    /// ディレクトリ直下のエントリを名前順で返す
+    /// どのWalkerでも子の順序が同じになるようにソートしておく
+    /// `--one-file-system`で辿らないマウントポイントは空として扱う
+    pub fn read_dir(&self, entry: &Entry) -> Option<Vec<PathBuf>> {
+        if entry.mount_point {
+            return Some(vec![]);
+        }
+        let mut paths: Vec<PathBuf> = fs::read_dir(&entry.path)
+            .map_err(|e| eprintln!("ERROR: {}", e))
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .collect();
+        paths.sort();
+        Some(paths)
+    }
+
+    /// ## Summary
+    /// git status・詳細情報・サイズを付与してTreeNodeを作る
+    /// ディレクトリのサイズは子のサイズを合計して求めるので、サブツリーを読み直すことはない
+    ///
+    /// ## Parameters
+    /// - `entry`: 対象のエントリ
+    /// - `children`: 走査済みの子。ファイルの場合は空
+    pub fn finish(&self, entry: Entry, children: Vec<Visited>) -> Visited {
+        let bytes = self.bytes(&entry, &children);
+        let vervose_info = if self.options.verbose {
+            utils::files::get_metadata(&entry.path)
+                .map_err(|e| eprintln!("ERROR: {}", e))
+                .ok()
+                .map(|mut info| {
+                    // ディレクトリは`--size`と同じく表示対象の子の合計にする
+                    if let (true, Some(bytes)) = (entry.is_dir, bytes) {
+                        info.size = bytes;
+                    }
+                    info
+                })
+        } else {
+            None
+        };
+        let path = entry.path.clone();
+        let depth = entry.depth;
+        let repository = if self.options.git_integration && entry.is_dir && entry.depth > 1 {
+            self.repository(&path)
+        } else {
+            None
+        };
+
+        let mut visited = self.assemble(entry, children, bytes, vervose_info);
+        if let Visited::Node(node) = &mut visited {
+            node.repository = repository;
+            if !self.time_kinds.is_empty() {
+                node.timestamps = utils::files::get_timestamps(&path, &self.time_kinds)
+                    .map_err(|e| eprintln!("ERROR: {}", e))
+                    .unwrap_or_default();
+            }
+        }
+        self.emit(&path, depth, visited)
+    }
+
+    /// ## Summary
+    /// `sink`があればノードを書き出し、ツリーからは取り除く
+    /// 書き出したノードはメモリに残さないので、巨大なツリーでも使用量が増えない
+    ///
+    /// ## Returns
+    /// 書き出した場合は`Visited::Emitted`, そうでなければ`visited`をそのまま返す
+    pub fn emit(&self, path: &Path, depth: u32, visited: Visited) -> Visited {
+        let (Some(sink), Visited::Node(node)) = (&self.sink, &visited) else {
+            return visited;
+        };
+        let relative = path.strip_prefix(&self.options.path).unwrap_or(path);
+        sink.write(node, relative, depth);
+        Visited::Emitted(visited.bytes())
+    }
+
+    /// ## Summary
+    /// 走査済みのエントリとサイズからTreeNodeを作る
+    /// ディスクから読む情報は呼び出し側で求めて渡すので、ディスク以外から作るツリーでも使える
+    ///
+    /// ## Parameters
+    /// - `bytes`: エントリのバイト数。サイズを表示しない場合はNone
+    pub fn assemble(
+        &self,
+        entry: Entry,
+        children: Vec<Visited>,
+        bytes: Option<u64>,
+        vervose_info: Option<MetaDataInfo>,
+    ) -> Visited {
+        if entry.is_dir {
+            self.forget_ignore_rules(&entry.path);
+        }
+        let absolute = self.absolute(&entry.path);
+        let ignored = self.is_ignored(&absolute);
+        let git_status = match self.git_statuses.get(&absolute) {
+            Some(status) => Some(*status),
+            // 無視されたディレクトリの中は辿らないので、祖先から引き継ぐ
+            None if ignored => Some(GitStatus::IGNORED),
+            None => None,
+        };
+        let last_commit = self.last_commits.get(&absolute).cloned();
+        let churn = self.churn.get(&absolute).copied();
+        let size = self
+            .options
+            .size
+            .as_ref()
+            .zip(bytes)
+            .map(|(size_format, bytes)| match size_format {
+                SizeFormat::Bytes => size::Unit::Byte(bytes),
+                SizeFormat::HumanReadable => size::Unit::new(bytes),
+            });
+
+        let emitted = children
+            .iter()
+            .any(|child| matches!(child, Visited::Emitted(_)));
+        let children: Vec<TreeNode> = children
+            .into_iter()
+            .filter_map(Visited::into_node)
+            .collect();
+        // --extでは一致するファイルを含まないディレクトリを、--pruneでは空のディレクトリを表示しない
+        // マウントポイントは中を読まないだけなので残す
+        let prune = self.options.prune || !self.extensions.is_empty();
+        if prune
+            && entry.is_dir
+            && !entry.mount_point
+            && entry.depth > 1
+            && children.is_empty()
+            && !emitted
+        {
+            return Visited::Hidden(bytes.unwrap_or(0));
+        }
+        let children = if entry.is_dir && !children.is_empty() {
+            Some(children)
+        } else {
+            None
+        };
+
+        let mut node = TreeNode::new(entry.name, git_status, children, size, vervose_info);
+        node.bytes = bytes;
+        node.is_dir = entry.is_dir;
+        node.last_commit = last_commit;
+        node.ignored = ignored;
+        node.symlink = entry.symlink;
+        node.mount_point = entry.mount_point;
+        node.churn = churn;
+        Visited::Node(node)
+    }
+
+    /// ## Summary
+    /// gitで無視されているか。無視されたディレクトリの中にあるものも無視されている
+    fn is_ignored(&self, absolute: &Path) -> bool {
+        self.options.show_ignored
+            && absolute.ancestors().any(|path| {
+                self.git_statuses
+                    .get(path)
+                    .is_some_and(|status| status.is_ignored())
+            })
+    }
+
+    /// ## Summary
+    /// ディレクトリがサブモジュール・作業ツリー・入れ子のリポジトリのルートであればその情報を返す
+    fn repository(&self, path: &Path) -> Option<RepoBoundary> {
+        match self.submodules.get(&self.absolute(path)) {
+            Some(submodule) => Some(submodule.clone()),
+            None => submodule::nested_repository(path),
+        }
+    }
+
+    /// ## Summary
+    /// 走査中のパスを絶対パスにする
+    /// git statusのキーはワークツリーからの絶対パスなので、ルートの指定方法によらず引けるようにする
+    fn absolute(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.options.path) {
+            Ok(relative) => self.root_abs.join(relative),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+
+    fn bytes(&self, entry: &Entry, children: &[Visited]) -> Option<u64> {
+        self.options.size.as_ref()?;
+        if entry.is_dir {
+            Some(children.iter().map(Visited::bytes).sum())
+        } else {
+            // 辿らないリンクと壊れたリンクはリンク自身のサイズ
+            let metadata = match &entry.symlink {
+                Some(symlink) if !self.options.follow || symlink.broken => {
+                    entry.path.symlink_metadata()
+                }
+                _ => entry.path.metadata(),
+            };
+            metadata
+                .map(|m| m.len())
+                .map_err(|e| eprintln!("ERROR: {}", e))
+                .ok()
+        }
+    }
+}
+
+/// ## Summary
+/// 1エントリを走査した結果
+/// ほとんどのエントリはNodeになるので、Boxで包まずにそのまま持つ
+#[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Visited {
+    /// ツリーに表示するノード
+    Node(TreeNode),
+    /// 表示しないエントリ。親ディレクトリのサイズに加算するバイト数だけを持つ
+    Hidden(u64),
+    /// `--ndjson`で書き出し済みのエントリ。表示はされたが、バイト数だけを持つ
+    Emitted(u64),
+}
+
+impl Visited {
+    pub fn bytes(&self) -> u64 {
+        match self {
+            Visited::Node(node) => node.bytes.unwrap_or(0),
+            Visited::Hidden(bytes) | Visited::Emitted(bytes) => *bytes,
+        }
+    }
+
+    pub fn into_node(self) -> Option<TreeNode> {
+        match self {
+            Visited::Node(node) => Some(node),
+            Visited::Hidden(_) | Visited::Emitted(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{foramt::sizeformat::SizeFormat, git::status::StatusClass};
+
+    /// ## Summary
+    /// 各Walkerの比較用のディレクトリを作る
+    fn fixture() -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for d in ["src/utils", "docs", "target/debug", "empty", ".git"] {
+            fs::create_dir_all(root.join(d)).unwrap();
+        }
+        for (file, body) in [
+            ("Cargo.toml", "[package]"),
+            ("README.md", "# readme"),
+            ("src/main.rs", "fn main() {}"),
+            ("src/lib.rs", "pub mod utils;"),
+            ("src/utils/mod.rs", "pub mod files;"),
+            ("src/utils/files.rs", "pub fn files() {}"),
+            ("src/utils/notes.txt", "note"),
+            ("docs/guide.md", "guide"),
+            ("docs/.hidden", "hidden"),
+            ("target/debug/app", "binary"),
+            (".git/HEAD", "ref: refs/heads/main"),
+        ] {
+            fs::write(root.join(file), body).unwrap();
+        }
+        dir
+    }
+
+    fn walk_all(options: &TreeOptions, root: &Path) -> Vec<Option<TreeNode>> {
+        [Mode::Sync, Mode::Parallel, Mode::Async]
+            .iter()
+            .map(|mode| {
+                let pipeline = Pipeline::new(options.clone().path(root), HashMap::new()).unwrap();
+                walker_for(mode, pipeline, 4).walk(root)
+            })
+            .collect()
+    }
+
+    fn assert_conformance(options: TreeOptions) {
+        let dir = fixture();
+        let trees = walk_all(&options, dir.path());
+
+        assert!(trees[0].is_some());
+        assert_eq!(trees[0], trees[1], "sync and parallel differ");
+        assert_eq!(trees[0], trees[2], "sync and async differ");
+    }
+
+    #[test]
+    fn test_walkers_conform_default() {
+        assert_conformance(TreeOptions::default());
+    }
+
+    #[test]
+    fn test_walkers_conform_with_size() {
+        assert_conformance(TreeOptions::default().size(SizeFormat::Bytes));
+        assert_conformance(TreeOptions::default().size(SizeFormat::HumanReadable));
+    }
+
+    #[test]
+    fn test_walkers_conform_with_filters() {
+        assert_conformance(
+            TreeOptions::default()
+                .ignores(["target"])
+                .extensions(["txt", "md"]),
+        );
+    }
+
+    #[test]
+    fn test_walkers_conform_with_depth_and_hidden() {
+        assert_conformance(TreeOptions::default().max_depth(2).show_hidden(true));
+    }
+
+    #[test]
+    fn test_walkers_conform_with_verbose() {
+        assert_conformance(TreeOptions::default().verbose(true));
+    }
+
+    #[test]
+    fn test_walkers_conform_with_filtered_sizes() {
+        assert_conformance(
+            TreeOptions::default()
+                .size(SizeFormat::Bytes)
+                .ignores(["target"])
+                .size_filtered(true),
+        );
+        assert_conformance(TreeOptions::default().size(SizeFormat::Bytes).max_depth(1));
+    }
+
+    fn bytes_of(node: &TreeNode) -> u64 {
+        node.get_bytes().unwrap()
+    }
+
+    #[test]
+    fn test_directory_size_is_sum_of_children() {
+        let dir = fixture();
+        let options = TreeOptions::default().size(SizeFormat::Bytes);
+        let root = SyncWalker::new(Pipeline::new(options, HashMap::new()).unwrap())
+            .walk(dir.path())
+            .unwrap();
+
+        let children = root.get_children().as_ref().unwrap();
+        assert_eq!(bytes_of(&root), children.iter().map(bytes_of).sum::<u64>());
+        // 表示されない.gitと.hiddenは含まない
+        assert_eq!(bytes_of(&root), 89);
+    }
+
+    #[test]
+    fn test_filtered_sizes_are_counted_when_requested() {
+        let dir = fixture();
+        let options = TreeOptions::default()
+            .size(SizeFormat::Bytes)
+            .ignores(["target"]);
+
+        let root = SyncWalker::new(Pipeline::new(options.clone(), HashMap::new()).unwrap())
+            .walk(dir.path())
+            .unwrap();
+        assert_eq!(bytes_of(&root), 83);
+
+        let root =
+            SyncWalker::new(Pipeline::new(options.size_filtered(true), HashMap::new()).unwrap())
+                .walk(dir.path())
+                .unwrap();
+        // target/debug/app, docs/.hidden, .git/HEAD
+        assert_eq!(bytes_of(&root), 83 + 6 + 6 + 20);
+    }
+
+    #[test]
+    fn test_depth_limited_directory_counts_its_contents() {
+        let dir = fixture();
+        let options = TreeOptions::default().size(SizeFormat::Bytes).max_depth(2);
+        let root = SyncWalker::new(Pipeline::new(options, HashMap::new()).unwrap())
+            .walk(dir.path())
+            .unwrap();
+
+        let src = root
+            .get_children()
+            .iter()
+            .flatten()
+            .find(|child| child.get_name() == "src")
+            .unwrap();
+        assert!(src.get_children().is_none());
+        assert_eq!(bytes_of(src), 12 + 14 + 14 + 17 + 4);
+    }
+
+    #[test]
+    fn test_depth_limited_sizes_match_full_walk() {
+        let dir = fixture();
+        git2::Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".gitignore"), "*.txt\n").unwrap();
+        let options = TreeOptions::default()
+            .size(SizeFormat::Bytes)
+            .ignores(["target"]);
+
+        let full = walk_all(&options, dir.path());
+        let truncated = walk_all(&options.clone().max_depth(2), dir.path());
+        for (full, truncated) in full.iter().zip(&truncated) {
+            let (full, truncated) = (full.as_ref().unwrap(), truncated.as_ref().unwrap());
+            // .git・.hidden・target・gitignoreされたnotes.txtはどちらでも数えない
+            assert_eq!(bytes_of(full), 83 - 4);
+            assert_eq!(bytes_of(truncated), bytes_of(full));
+            assert_eq!(
+                bytes_of(child(truncated, "src")),
+                bytes_of(child(full, "src"))
+            );
+        }
+
+        // --size-filteredではどちらも除外したものを含める
+        let options = options.size_filtered(true);
+        let full = walk_all(&options, dir.path());
+        let truncated = walk_all(&options.clone().max_depth(1), dir.path());
+        assert_eq!(
+            bytes_of(truncated[0].as_ref().unwrap()),
+            bytes_of(full[0].as_ref().unwrap())
+        );
+    }
+
+    fn child<'a>(node: &'a TreeNode, name: &str) -> &'a TreeNode {
+        node.get_children()
+            .iter()
+            .flatten()
+            .find(|child| child.get_name() == name)
+            .unwrap()
+    }
+
+    fn names(node: &TreeNode) -> Vec<&str> {
+        node.get_children()
+            .iter()
+            .flatten()
+            .map(|child| child.get_name())
+            .collect()
+    }
+
+    #[test]
+    fn test_walkers_conform_with_gitignore() {
+        let dir = fixture();
+        git2::Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n*.txt\n").unwrap();
+
+        let trees = walk_all(&TreeOptions::default(), dir.path());
+        assert_eq!(trees[0], trees[1]);
+        assert_eq!(trees[0], trees[2]);
+
+        let root = trees[0].as_ref().unwrap();
+        assert!(!names(root).contains(&"target"));
+        let utils = child(child(root, "src"), "utils");
+        assert_eq!(names(utils), vec!["files.rs", "mod.rs"]);
+    }
+
+    #[test]
+    fn test_no_ignore_disables_gitignore() {
+        let dir = fixture();
+        git2::Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+
+        let trees = walk_all(&TreeOptions::default().gitignore(false), dir.path());
+        assert!(names(trees[0].as_ref().unwrap()).contains(&"target"));
+    }
+
+    #[test]
+    fn test_gitignore_outside_repository_only_when_forced() {
+        let dir = fixture();
+        fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+
+        let trees = walk_all(&TreeOptions::default(), dir.path());
+        assert!(names(trees[0].as_ref().unwrap()).contains(&"target"));
+
+        let trees = walk_all(&TreeOptions::default().gitignore(true), dir.path());
+        assert!(!names(trees[0].as_ref().unwrap()).contains(&"target"));
+    }
+
+    #[test]
+    fn test_walkers_conform_with_patterns() {
+        assert_conformance(
+            TreeOptions::default()
+                .ignores(["*.md", "src/utils/*.txt"])
+                .includes(["*.rs", "*.txt", "Cargo.*"]),
+        );
+        assert_conformance(
+            TreeOptions::default()
+                .regex(true)
+                .ignores([r"^target$"])
+                .includes([r"\.rs$"]),
+        );
+    }
+
+    #[test]
+    fn test_ignore_glob_matches_relative_path() {
+        let dir = fixture();
+        let options = TreeOptions::default().ignores(["src/**/*.txt", "*.md"]);
+        let root = walk_all(&options, dir.path()).remove(0).unwrap();
+
+        assert_eq!(names(child(&root, "docs")), Vec::<&str>::new());
+        assert_eq!(
+            names(child(child(&root, "src"), "utils")),
+            vec!["files.rs", "mod.rs"]
+        );
+    }
+
+    #[test]
+    fn test_include_keeps_directories() {
+        let dir = fixture();
+        let options = TreeOptions::default().includes(["*.txt"]);
+        let root = walk_all(&options, dir.path()).remove(0).unwrap();
+
+        assert_eq!(names(&root), vec!["docs", "empty", "src", "target"]);
+        assert_eq!(
+            names(child(child(&root, "src"), "utils")),
+            vec!["notes.txt"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_error() {
+        let options = TreeOptions::default().ignores(["a[b"]);
+        assert!(Pipeline::new(options, HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_git_changed() {
+        let dir = fixture();
+        let root = dir.path();
+        let repo = git2::Repository::init(root).unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() { todo!() }").unwrap();
+        fs::write(root.join("docs/guide.md"), "staged").unwrap();
+        index.add_path(Path::new("docs/guide.md")).unwrap();
+        index.write().unwrap();
+        fs::write(root.join("src/utils/new.rs"), "").unwrap();
+        let statuses = crate::git::status::get_git_statuses(root, false);
+
+        let walk = |classes: &[StatusClass]| {
+            let options = TreeOptions::new(root).git_changed(classes.iter().copied());
+            let trees: Vec<_> = [Mode::Sync, Mode::Parallel, Mode::Async]
+                .iter()
+                .map(|mode| {
+                    let pipeline = Pipeline::new(options.clone(), statuses.clone()).unwrap();
+                    walker_for(mode, pipeline, 4).walk(root).unwrap()
+                })
+                .collect();
+            assert_eq!(trees[0], trees[1]);
+            assert_eq!(trees[0], trees[2]);
+            trees.into_iter().next().unwrap()
+        };
+
+        let all = walk(&[]);
+        assert_eq!(names(&all), ["docs", "src"]);
+        assert_eq!(names(child(&all, "src")), ["main.rs", "utils"]);
+        assert_eq!(names(child(child(&all, "src"), "utils")), ["new.rs"]);
+
+        let untracked = walk(&[StatusClass::Untracked]);
+        assert_eq!(names(&untracked), ["src"]);
+        assert_eq!(names(child(&untracked, "src")), ["utils"]);
+
+        let staged = walk(&[StatusClass::Staged]);
+        assert_eq!(names(&staged), ["docs"]);
+        assert_eq!(names(child(&staged, "docs")), ["guide.md"]);
+
+        let worktree = walk(&[StatusClass::Worktree]);
+        assert_eq!(names(&worktree), ["src"]);
+        assert_eq!(names(child(&worktree, "src")), ["main.rs"]);
+    }
+
+    #[test]
+    fn test_git_status_in_subdirectory() {
+        let dir = fixture();
+        git2::Repository::init(dir.path()).unwrap();
+        let src = dir.path().join("src");
+        let statuses = crate::git::status::get_git_statuses(&src, false);
+        assert!(!statuses.is_empty());
+
+        for mode in [Mode::Sync, Mode::Parallel, Mode::Async] {
+            let options = TreeOptions::new(&src);
+            let pipeline = Pipeline::new(options, statuses.clone()).unwrap();
+            let root = walker_for(&mode, pipeline, 2).walk(&src).unwrap();
+
+            assert_eq!(
+                child(&root, "main.rs").get_git_status(),
+                Some(GitStatus::UNTRACKED)
+            );
+            assert_eq!(root.get_git_status(), Some(GitStatus::UNTRACKED));
+        }
+    }
+
+    #[test]
+    fn test_show_ignored() {
+        let dir = fixture();
+        let root = dir.path();
+        git2::Repository::init(root).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join("src/build.log"), "").unwrap();
+
+        let hidden = crate::build_tree(&TreeOptions::new(root)).unwrap();
+        assert!(!names(&hidden).contains(&"target"));
+
+        for mode in [Mode::Sync, Mode::Parallel, Mode::Async] {
+            let options = TreeOptions::new(root).show_ignored(true).mode(mode);
+            let tree = crate::build_tree(&options).unwrap();
+
+            let target = child(&tree, "target");
+            assert!(target.is_ignored());
+            assert_eq!(target.get_git_status(), Some(GitStatus::IGNORED));
+            let app = child(child(target, "debug"), "app");
+            assert!(app.is_ignored());
+            assert_eq!(app.get_git_status(), Some(GitStatus::IGNORED));
+            assert!(child(child(&tree, "src"), "build.log").is_ignored());
+
+            // 無視されていないファイルには状態を付けない
+            let main = child(child(&tree, "src"), "main.rs");
+            assert!(!main.is_ignored());
+            assert_eq!(main.get_git_status(), None);
+            assert!(!child(&tree, "src").is_ignored());
+
+            let json = serde_json::to_value(target).unwrap();
+            assert_eq!(json["ignored"], true);
+            assert!(serde_json::to_value(main).unwrap().get("ignored").is_none());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = fixture();
+        let root = dir.path();
+        symlink("../docs", root.join("src/docs")).unwrap();
+        symlink("..", root.join("src/utils/up")).unwrap();
+        symlink("missing.rs", root.join("src/dangling.rs")).unwrap();
+
+        let walk = |follow: bool| {
+            let options = TreeOptions::new(root).follow(follow);
+            let trees: Vec<_> = [Mode::Sync, Mode::Parallel, Mode::Async]
+                .iter()
+                .map(|mode| {
+                    let pipeline = Pipeline::new(options.clone(), HashMap::new()).unwrap();
+                    walker_for(mode, pipeline, 4).walk(root).unwrap()
+                })
+                .collect();
+            assert_eq!(trees[0], trees[1]);
+            assert_eq!(trees[0], trees[2]);
+            trees.into_iter().next().unwrap()
+        };
+
+        let tree = walk(false);
+        let src = child(&tree, "src");
+        let docs = child(src, "docs");
+        assert_eq!(docs.get_symlink().unwrap().target, "../docs");
+        assert!(docs.get_children().is_none());
+        let dangling = child(src, "dangling.rs").get_symlink().unwrap();
+        assert!(dangling.broken);
+        assert!(child(src, "main.rs").get_symlink().is_none());
+
+        let tree = walk(true);
+        let src = child(&tree, "src");
+        assert_eq!(names(child(src, "docs")), ["guide.md"]);
+        // 祖先に戻るリンクは辿らない
+        let up = child(child(src, "utils"), "up");
+        assert!(up.get_symlink().unwrap().recursive);
+        assert!(up.get_children().is_none());
+
+        let json = serde_json::to_value(child(src, "dangling.rs")).unwrap();
+        assert_eq!(json["symlink"]["target"], "missing.rs");
+        assert_eq!(json["symlink"]["broken"], true);
+    }
+
+    #[test]
+    fn test_one_file_system() {
+        let dir = fixture();
+        let root = dir.path();
+        let options = TreeOptions::new(root).one_file_system(true);
+        let pipeline = Pipeline::new(options.clone(), HashMap::new()).unwrap();
+        assert!(pipeline.root_device.is_some());
+        let same_device = SyncWalker::new(pipeline).walk(root).unwrap();
+        assert!(!child(&same_device, "src").is_mount_point());
+        assert_eq!(
+            names(child(&same_device, "src")),
+            ["lib.rs", "main.rs", "utils"]
+        );
+
+        // ルート以外のディレクトリがすべて別のデバイスにあるものとして走査する
+        for mode in [Mode::Sync, Mode::Parallel, Mode::Async] {
+            let mut pipeline = Pipeline::new(options.clone(), HashMap::new()).unwrap();
+            pipeline.root_device = Some(u64::MAX);
+            let tree = walker_for(&mode, pipeline, 2).walk(root).unwrap();
+
+            assert_eq!(
+                names(&tree),
+                ["Cargo.toml", "README.md", "docs", "empty", "src", "target"]
+            );
+            let src = child(&tree, "src");
+            assert!(src.is_mount_point());
+            assert!(src.is_dir());
+            assert!(src.get_children().is_none());
+            assert!(!child(&tree, "README.md").is_mount_point());
+            assert_eq!(serde_json::to_value(src).unwrap()["mount_point"], true);
+        }
+    }
+
+    #[test]
+    fn test_async_walker_with_single_job() {
+        let dir = fixture();
+        let options = TreeOptions::default();
+        let expected = SyncWalker::new(Pipeline::new(options.clone(), HashMap::new()).unwrap())
+            .walk(dir.path());
+        let actual =
+            AsyncWalker::new(Pipeline::new(options, HashMap::new()).unwrap(), 1).walk(dir.path());
+
+        assert!(expected.is_some());
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_async_walker_deep_tree_with_single_job() {
+        let dir = tempfile::tempdir().unwrap();
+        let deepest: PathBuf = (0..32).map(|i| format!("d{}", i)).collect();
+        fs::create_dir_all(dir.path().join(&deepest)).unwrap();
+        fs::write(dir.path().join(&deepest).join("leaf.txt"), "leaf").unwrap();
+        let options = TreeOptions::default().size(SizeFormat::Bytes);
+
+        // 親が子の完了を待つ間permitを持たないので、permitが1つでも最後まで辿れる
+        let tree =
+            AsyncWalker::new(Pipeline::new(options, HashMap::new()).unwrap(), 1).walk(dir.path());
+        assert_eq!(tree.unwrap().bytes, Some(4));
+    }
+
+    #[test]
+    fn test_ext_keeps_only_directories_with_matches() {
+        let dir = fixture();
+        let options = TreeOptions::default().extensions(["txt"]);
+
+        for tree in walk_all(&options, dir.path()) {
+            let root = tree.unwrap();
+            assert_eq!(names(&root), vec!["src"]);
+            assert_eq!(
+                names(child(child(&root, "src"), "utils")),
+                vec!["notes.txt"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_ext_is_case_insensitive_and_multi_part() {
+        let dir = fixture();
+        fs::write(dir.path().join("backup.TAR.GZ"), "").unwrap();
+        fs::write(dir.path().join("backup.gz"), "").unwrap();
+        let options = TreeOptions::default().extensions(["tar.gz", "MD"]);
+        let root = walk_all(&options, dir.path()).remove(0).unwrap();
+
+        assert_eq!(names(&root), vec!["README.md", "backup.TAR.GZ", "docs"]);
+    }
+
+    #[test]
+    fn test_exclude_ext() {
+        let dir = fixture();
+        let options = TreeOptions::default().exclude_extensions(["rs"]);
+        let root = walk_all(&options, dir.path()).remove(0).unwrap();
+
+        assert_eq!(names(child(&root, "src")), vec!["utils"]);
+        assert_eq!(
+            names(child(child(&root, "src"), "utils")),
+            vec!["notes.txt"]
+        );
+    }
+
+    #[test]
+    fn test_prune_drops_directories_without_matches() {
+        let dir = fixture();
+        let options = TreeOptions::default().extensions(["txt"]).prune(true);
+        let trees = walk_all(&options, dir.path());
+        assert_eq!(trees[0], trees[1]);
+        assert_eq!(trees[0], trees[2]);
+
+        let root = trees[0].as_ref().unwrap();
+        assert_eq!(names(root), vec!["src"]);
+        assert_eq!(names(child(root, "src")), vec!["utils"]);
+    }
+
+    #[test]
+    fn test_prune_drops_empty_directories() {
+        let dir = fixture();
+        let options = TreeOptions::new(dir.path()).prune(true);
+        let root = walk_all(&options, dir.path()).remove(0).unwrap();
+        assert_eq!(
+            names(&root),
+            vec!["Cargo.toml", "README.md", "docs", "src", "target"]
+        );
+
+        // マウントポイントは中を読まないだけなので残す
+        let mut pipeline = Pipeline::new(options.one_file_system(true), HashMap::new()).unwrap();
+        pipeline.root_device = Some(u64::MAX);
+        let root = SyncWalker::new(pipeline).walk(dir.path()).unwrap();
+        assert_eq!(
+            names(&root),
+            vec!["Cargo.toml", "README.md", "docs", "empty", "src", "target"]
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use crate::{tree::TreeNode, utils::files::FileId};
+
+use super::{Pipeline, Visited, Walker};
+
+/// ## Summary
+/// rayonで子エントリを並列に走査するWalker
+/// `par_iter`は順序を保ったままcollectするので結果は同期版と同じになる
+#[derive(Debug)]
+pub struct ParallelWalker {
+    pipeline: Pipeline,
+    jobs: usize,
+}
+
+impl ParallelWalker {
+    pub fn new(pipeline: Pipeline, jobs: usize) -> Self {
+        Self {
+            pipeline,
+            jobs: jobs.max(1),
+        }
+    }
+
+    fn walk_entry(&self, path: &Path, depth: u32, ancestors: &[FileId]) -> Visited {
+        let entry = match self.pipeline.accept(path, depth, ancestors) {
+            Ok(entry) => entry,
+            Err(bytes) => return Visited::Hidden(bytes),
+        };
+
+        let children = if entry.is_dir {
+            let Some(paths) = self.pipeline.read_dir(&entry) else {
+                return Visited::Hidden(0);
+            };
+            paths
+                .par_iter()
+                .map(|child| self.walk_entry(child, depth + 1, &entry.ancestors))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        self.pipeline.finish(entry, children)
+    }
+}
+
+impl Walker for ParallelWalker {
+    fn walk(&self, root: &Path) -> Option<TreeNode> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.jobs)
+            .build()
+            .map_err(|e| eprintln!("ERROR: {}", e))
+            .ok()?;
+        pool.install(|| self.walk_entry(root, 1, &[])).into_node()
+    }
+}
@@ -0,0 +1,343 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use git2::{ObjectType, Oid, Repository, Tree, TreeEntry};
+
+use crate::{
+    git::{object::GitObject, open_repository},
+    tree::TreeNode,
+    utils,
+};
+
+use super::{Entry, Pipeline, Visited, Walker};
+
+/// ## Summary
+/// ワークツリーではなくgitのコミット・ブランチ・タグのツリーを走査するWalker
+/// フィルター・サイズ・`--prune`はディスクを走査する場合と同じ`Pipeline`で処理する
+pub struct RevWalker {
+    pipeline: Pipeline,
+    repo: Repository,
+    tree: Oid,
+}
+
+impl RevWalker {
+    /// ## Summary
+    /// `rev`が指すツリーのうち、走査を開始するパスに対応する部分を開く
+    ///
+    /// ## Returns
+    /// RevWalker or リポジトリが見つからない・`rev`が解決できない・パスがツリーにない場合のError
+    pub fn open(pipeline: Pipeline, rev: &str) -> Result<Self> {
+        let path = pipeline.options().path.clone();
+        let repo = open_repository_at(&path)?;
+        let tree = resolve_tree(&repo, &path, rev)?;
+
+        Ok(Self {
+            pipeline,
+            repo,
+            tree,
+        })
+    }
+
+    fn visit_entry(&self, tree_entry: &TreeEntry, path: &Path, depth: u32) -> Visited {
+        let is_dir = tree_entry.kind() == Some(ObjectType::Tree);
+        if self.pipeline.is_too_deep(depth) {
+            // 深さで切り捨てたエントリも親ディレクトリの中身なので常にサイズに含める
+            return Visited::Hidden(self.hidden(tree_entry, true));
+        }
+        let name = String::from_utf8_lossy(tree_entry.name_bytes()).into_owned();
+        if self.pipeline.is_filtered(path, &name, is_dir, depth) {
+            return Visited::Hidden(self.hidden(tree_entry, self.pipeline.counts_filtered_size()));
+        }
+
+        let object = GitObject::from_entry(&self.repo, tree_entry);
+        let children = if is_dir {
+            match self.repo.find_tree(tree_entry.id()) {
+                Ok(tree) => self.visit_children(&tree, path, depth),
+                Err(e) => {
+                    eprintln!("ERROR: {}", e.message());
+                    return Visited::Hidden(0);
+                }
+            }
+        } else {
+            vec![]
+        };
+        let bytes = self.bytes(tree_entry, &children);
+
+        let entry = Entry {
+            path: path.to_path_buf(),
+            name,
+            depth,
+            is_dir,
+            ..Default::default()
+        };
+        let mut visited = self.pipeline.assemble(entry, children, bytes, None);
+        if let Visited::Node(node) = &mut visited {
+            node.git_object = Some(object);
+        }
+        self.pipeline.emit(path, depth, visited)
+    }
+
+    /// ## Summary
+    /// ツリー直下のエントリを名前順で走査する
+    fn visit_children(&self, tree: &Tree, path: &Path, depth: u32) -> Vec<Visited> {
+        sorted_entries(tree)
+            .iter()
+            .map(|child| {
+                let child_path = path.join(String::from_utf8_lossy(child.name_bytes()).as_ref());
+                self.visit_entry(child, &child_path, depth + 1)
+            })
+            .collect()
+    }
+
+    fn bytes(&self, tree_entry: &TreeEntry, children: &[Visited]) -> Option<u64> {
+        self.pipeline.options().size.as_ref()?;
+        match tree_entry.kind() {
+            Some(ObjectType::Tree) => Some(children.iter().map(Visited::bytes).sum()),
+            Some(ObjectType::Blob) => Some(blob_size(&self.repo, tree_entry.id())),
+            _ => Some(0),
+        }
+    }
+
+    /// ## Summary
+    /// ツリーに表示しないエントリのサイズを求める
+    fn hidden(&self, tree_entry: &TreeEntry, count_size: bool) -> u64 {
+        if self.pipeline.options().size.is_none() || !count_size {
+            return 0;
+        }
+        entry_bytes(&self.repo, tree_entry)
+    }
+}
+
+impl Walker for RevWalker {
+    fn walk(&self, root: &Path) -> Option<TreeNode> {
+        let tree = self
+            .repo
+            .find_tree(self.tree)
+            .map_err(|e| eprintln!("ERROR: {}", e.message()))
+            .ok()?;
+        let children = self.visit_children(&tree, root, 1);
+        let bytes = self
+            .pipeline
+            .options()
+            .size
+            .as_ref()
+            .map(|_| children.iter().map(Visited::bytes).sum());
+
+        let entry = Entry {
+            path: root.to_path_buf(),
+            name: utils::files::get_filename(root),
+            depth: 1,
+            is_dir: true,
+            ..Default::default()
+        };
+        let mut node = self
+            .pipeline
+            .assemble(entry, children, bytes, None)
+            .into_node()?;
+        node.git_object = Some(GitObject::tree(self.tree));
+        self.pipeline.emit(root, 1, Visited::Node(node)).into_node()
+    }
+}
+
+/// ## Summary
+/// `path`を含むリポジトリを開く
+pub(super) fn open_repository_at(path: &Path) -> Result<Repository> {
+    open_repository(path).map_err(|e| {
+        anyhow!(
+            "cannot open repository at {}: {}",
+            path.display(),
+            e.message()
+        )
+    })
+}
+
+/// ## Summary
+/// `rev`のツリーから`path`に対応するサブツリーのIDを求める
+pub(super) fn resolve_tree(repo: &Repository, path: &Path, rev: &str) -> Result<Oid> {
+    let tree = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_tree())
+        .map_err(|e| anyhow!("cannot resolve revision {}: {}", rev, e.message()))?;
+
+    // bareリポジトリにはワークツリーがないのでツリー全体を表示する
+    match repo.workdir() {
+        Some(workdir) => {
+            let workdir = workdir
+                .canonicalize()
+                .unwrap_or_else(|_| workdir.to_path_buf());
+            let relative = path
+                .canonicalize()?
+                .strip_prefix(&workdir)
+                .map(Path::to_path_buf)
+                .map_err(|_| anyhow!("{} is outside of the repository", path.display()))?;
+            if relative.as_os_str().is_empty() {
+                Ok(tree.id())
+            } else {
+                tree.get_path(&relative)
+                    .and_then(|entry| entry.to_object(repo))
+                    .and_then(|object| object.peel_to_tree())
+                    .map_err(|e| {
+                        anyhow!(
+                            "{} is not a tree in {}: {}",
+                            relative.display(),
+                            rev,
+                            e.message()
+                        )
+                    })
+                    .map(|tree| tree.id())
+            }
+        }
+        None => Ok(tree.id()),
+    }
+}
+
+/// ## Summary
+/// ツリー直下のエントリを名前順で返す
+/// ディスクを走査する場合と子の順序が同じになるようにgitの並び順ではなく名前でソートする
+pub(super) fn sorted_entries(tree: &Tree) -> Vec<TreeEntry<'static>> {
+    let mut entries: Vec<TreeEntry<'static>> = tree.iter().map(|entry| entry.to_owned()).collect();
+    entries.sort_by(|a, b| a.name_bytes().cmp(b.name_bytes()));
+    entries
+}
+
+/// ## Summary
+/// エントリのバイト数を求める
+/// ツリーは再帰的に読み込んでblobのサイズを合計する
+pub(super) fn entry_bytes(repo: &Repository, tree_entry: &TreeEntry) -> u64 {
+    match tree_entry.kind() {
+        Some(ObjectType::Tree) => match repo.find_tree(tree_entry.id()) {
+            Ok(tree) => tree.iter().map(|child| entry_bytes(repo, &child)).sum(),
+            Err(e) => {
+                eprintln!("ERROR: {}", e.message());
+                0
+            }
+        },
+        Some(ObjectType::Blob) => blob_size(repo, tree_entry.id()),
+        _ => 0,
+    }
+}
+
+pub(super) fn blob_size(repo: &Repository, id: Oid) -> u64 {
+    repo.find_blob(id)
+        .map(|blob| blob.size() as u64)
+        .map_err(|e| eprintln!("ERROR: {}", e.message()))
+        .unwrap_or(0)
+}
+
+// シンボリックリンクと実行権限を含むコミットを作るのでunixでのみ実行する
+#[cfg(all(test, unix))]
+mod test {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    use git2::Signature;
+
+    use super::*;
+    use crate::{build_tree, git::object::GitObjectKind, SizeFormat, TreeOptions};
+
+    fn child<'a>(node: &'a TreeNode, name: &str) -> &'a TreeNode {
+        node.get_children()
+            .iter()
+            .flatten()
+            .find(|child| child.get_name() == name)
+            .unwrap()
+    }
+
+    fn names(node: &TreeNode) -> Vec<&str> {
+        node.get_children()
+            .iter()
+            .flatten()
+            .map(|child| child.get_name())
+            .collect()
+    }
+
+    /// ## Summary
+    /// ファイル・実行ファイル・シンボリックリンク・サブモジュールを含むコミットを作り、
+    /// その後ワークツリーを変更したリポジトリ
+    fn repository() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let repo = Repository::init(root).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("README.md"), "readme").unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("run.sh"), "#!/bin/sh").unwrap();
+        fs::set_permissions(root.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("README.md", root.join("link")).unwrap();
+
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let mut builder = repo.treebuilder(Some(&tree)).unwrap();
+        builder.insert("vendor", tree.id(), 0o160000).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature = Signature::now("test", "test@example.com").unwrap();
+        let commit = repo
+            .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        repo.tag_lightweight("v1", &repo.find_object(commit, None).unwrap(), false)
+            .unwrap();
+
+        fs::remove_file(root.join("src/main.rs")).unwrap();
+        fs::write(root.join("new.txt"), "new").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_rev_tree() {
+        let dir = repository();
+        let options = TreeOptions::new(dir.path())
+            .rev("v1")
+            .size(SizeFormat::Bytes);
+        let root = build_tree(&options).unwrap();
+
+        assert_eq!(
+            names(&root),
+            ["README.md", "link", "run.sh", "src", "vendor"]
+        );
+        assert_eq!(names(child(&root, "src")), ["main.rs"]);
+        assert_eq!(child(&root, "src").get_bytes(), Some(12));
+        assert_eq!(root.get_bytes(), Some(6 + 9 + 9 + 12));
+
+        let kind = |name: &str| child(&root, name).get_git_object().unwrap().kind;
+        assert_eq!(kind("README.md"), GitObjectKind::Blob);
+        assert_eq!(kind("run.sh"), GitObjectKind::Executable);
+        assert_eq!(kind("src"), GitObjectKind::Tree);
+        assert_eq!(kind("vendor"), GitObjectKind::Submodule);
+        let link = child(&root, "link").get_git_object().unwrap();
+        assert_eq!(link.kind, GitObjectKind::Symlink);
+        assert_eq!(link.mode, "120000");
+        assert_eq!(link.target.as_deref(), Some("README.md"));
+    }
+
+    #[test]
+    fn test_rev_tree_with_filters() {
+        let dir = repository();
+        let options = TreeOptions::new(dir.path())
+            .rev("HEAD")
+            .extensions(["rs"])
+            .prune(true);
+        let root = build_tree(&options).unwrap();
+
+        assert_eq!(names(&root), ["src"]);
+        assert_eq!(names(child(&root, "src")), ["main.rs"]);
+    }
+
+    #[test]
+    fn test_rev_subdirectory() {
+        let dir = repository();
+        let options = TreeOptions::new(dir.path().join("src")).rev("v1");
+        let root = build_tree(&options).unwrap();
+
+        assert_eq!(root.get_name(), "src");
+        assert_eq!(names(&root), ["main.rs"]);
+    }
+
+    #[test]
+    fn test_unknown_rev_is_error() {
+        let dir = repository();
+        let options = TreeOptions::new(dir.path()).rev("no-such-branch");
+        assert!(build_tree(&options).is_err());
+    }
+}
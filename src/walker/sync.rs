@@ -0,0 +1,45 @@
+use std::path::Path;
+
+use crate::{tree::TreeNode, utils::files::FileId};
+
+use super::{Pipeline, Visited, Walker};
+
+/// ## Summary
+/// 1スレッドで再帰的に走査するWalker
+#[derive(Debug)]
+pub struct SyncWalker {
+    pipeline: Pipeline,
+}
+
+impl SyncWalker {
+    pub fn new(pipeline: Pipeline) -> Self {
+        Self { pipeline }
+    }
+
+    fn walk_entry(&self, path: &Path, depth: u32, ancestors: &[FileId]) -> Visited {
+        let entry = match self.pipeline.accept(path, depth, ancestors) {
+            Ok(entry) => entry,
+            Err(bytes) => return Visited::Hidden(bytes),
+        };
+
+        let children = if entry.is_dir {
+            let Some(paths) = self.pipeline.read_dir(&entry) else {
+                return Visited::Hidden(0);
+            };
+            paths
+                .iter()
+                .map(|child| self.walk_entry(child, depth + 1, &entry.ancestors))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        self.pipeline.finish(entry, children)
+    }
+}
+
+impl Walker for SyncWalker {
+    fn walk(&self, root: &Path) -> Option<TreeNode> {
+        self.walk_entry(root, 1, &[]).into_node()
+    }
+}